@@ -0,0 +1,139 @@
+use std::{hint::black_box, sync::Arc};
+
+use bevy::math::{IVec3, UVec3};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use enum_map::enum_map;
+use fastnoise_lite::FastNoiseLite;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use voxel::{
+    block_lib::{Block, BlockLibrary, InnerBlockLibrary, state::BlockState},
+    chunk::{Chunk, Mesher, MesherStats, VoxelIndex, Voxels, pad},
+    math::signed_axis::SignedAxis,
+};
+
+// Every chunk fills its padding the same way as its interior, as if its
+// neighbours were generated alike. Stats for each case are printed once
+// before it is timed.
+
+const STONE: usize = 0;
+const DIRT: usize = 1;
+const GLASS: usize = 2;
+const LEAVES: usize = 3;
+
+fn block(display_name: &str, texture: u32, is_transparent: bool, connects_to_same: bool) -> Block {
+    Block {
+        display_name: display_name.to_string(),
+        collision_aabbs: Vec::new(),
+        is_transparent,
+        connects_to_same,
+        emission: 0,
+        light_opacity: if is_transparent { 0 } else { 15 },
+        textures: enum_map! { _ => texture },
+        model: None,
+        state: BlockState::default(),
+    }
+}
+
+fn block_library() -> BlockLibrary {
+    BlockLibrary(Arc::new(InnerBlockLibrary::from_blocks([
+        ("bench:stone", block("Stone", 0, false, false)),
+        ("bench:dirt", block("Dirt", 1, false, false)),
+        ("bench:glass", block("Glass", 2, true, true)),
+        ("bench:leaves", block("Leaves", 3, true, false)),
+    ])))
+}
+
+fn chunk(block_library: &BlockLibrary, mut f: impl FnMut(IVec3) -> Option<usize>) -> Chunk {
+    let mut voxels = Voxels::EMPTY;
+
+    for index in 0..pad::VOL {
+        let pos: UVec3 = pad::delinearize(index);
+        voxels.set(index, f(pos.as_ivec3()).and_then(VoxelIndex::new));
+    }
+
+    voxels.compact();
+    Chunk::from_voxels(voxels, block_library)
+}
+
+fn cases(block_library: &BlockLibrary) -> Vec<(&'static str, Chunk)> {
+    let mut noise = FastNoiseLite::with_seed(0);
+    noise.set_frequency(Some(0.02));
+
+    let mut rng = StdRng::seed_from_u64(0);
+
+    vec![
+        ("empty", chunk(block_library, |_| None)),
+        ("full", chunk(block_library, |_| Some(STONE))),
+        (
+            "checkerboard",
+            chunk(block_library, |p| {
+                ((p.x + p.y + p.z) % 2 == 0).then_some(STONE)
+            }),
+        ),
+        (
+            "noise_terrain",
+            chunk(block_library, |p| {
+                let height = 32.0 + noise.get_noise_2d(p.x as f32, p.z as f32) * 24.0;
+                match p.y as f32 {
+                    y if y < height - 3.0 => Some(STONE),
+                    y if y < height => Some(DIRT),
+                    _ => None,
+                }
+            }),
+        ),
+        (
+            "transparent_mix",
+            chunk(block_library, |_| match rng.random_range(0..8) {
+                0 | 1 => Some(STONE),
+                2 | 3 => Some(GLASS),
+                4 => Some(LEAVES),
+                _ => None,
+            }),
+        ),
+    ]
+}
+
+fn report(name: &str, stats: &MesherStats) {
+    let opaque = SignedAxis::ALL.map(|signed_axis| stats.opaque_quads[signed_axis]);
+    let transparent = SignedAxis::ALL.map(|signed_axis| stats.transparent_quads[signed_axis]);
+
+    println!(
+        "{name}: {} faces, {} quads ({:.2} faces per quad), opaque {opaque:?}, transparent {transparent:?}, culling {:?}, merging {:?}",
+        stats.faces,
+        stats.quads,
+        stats.merge_ratio(),
+        stats.culling,
+        stats.merging,
+    );
+}
+
+fn mesh(c: &mut Criterion) {
+    let block_library = block_library();
+    let mut mesher = Mesher::new();
+
+    let mut group = c.benchmark_group("mesh");
+
+    for (name, chunk) in cases(&block_library) {
+        mesher.clear();
+        mesher.stats = Some(MesherStats::default());
+        mesher.mesh(&chunk, IVec3::ZERO, &block_library, None);
+
+        let stats = mesher.stats.take().unwrap_or_default();
+        report(name, &stats);
+
+        group.throughput(Throughput::Elements(stats.quads as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                mesher.clear();
+                let (quads, offsets) =
+                    mesher.mesh(black_box(&chunk), IVec3::ZERO, &block_library, None);
+                black_box((quads.len(), offsets.transparent()));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, mesh);
+criterion_main!(benches);
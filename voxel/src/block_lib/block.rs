@@ -3,17 +3,25 @@ use bevy::{
     platform::collections::HashMap,
     prelude::*,
 };
+use std::sync::Arc;
 
 use crate::math::signed_axis::SignedAxisMap;
 
-use super::{intermediate::IntermediateBlock, Interner, Identifier};
+use super::{intermediate::IntermediateBlock, model::ModelFace, state::BlockState, Interner, Identifier};
 
 #[derive(Debug, Clone)]
 pub struct Block {
     pub display_name: String,
     pub collision_aabbs: Vec<Aabb3d>,
     pub is_transparent: bool,
+    pub connects_to_same: bool,
+    pub emission: u8,
+    pub light_opacity: u8,
     pub textures: SignedAxisMap<u32>,
+    /// `None` for cubes, see `model.rs`.
+    pub model: Option<Arc<[ModelFace]>>,
+    /// Empty for blocks without properties.
+    pub state: BlockState,
 }
 
 impl Block {
@@ -26,9 +34,19 @@ impl Block {
             display_name,
             collision_aabbs,
             is_transparent,
+            connects_to_same,
+            emission,
+            light_opacity,
+            model,
             textures: texture_names,
+            properties: _,
         } = intermediate.clone();
 
+        let emission = emission.min(15);
+        let light_opacity = light_opacity
+            .unwrap_or(if is_transparent || !model.is_cube() { 0 } else { 15 })
+            .min(15);
+
         let texture_names = texture_names.map(|_, n| interner.get_or_intern(&n));
 
         let opt_textures = texture_names.map(|_, s| tex_name_to_index.get(&s));
@@ -39,11 +57,32 @@ impl Block {
 
         let textures = opt_textures.map(|_, opt| *opt.unwrap());
 
+        let model = model.faces(&textures).map(Arc::from);
+
         Some(Self {
             display_name,
             collision_aabbs,
             is_transparent,
+            connects_to_same,
+            emission,
+            light_opacity,
             textures,
+            model,
+            state: BlockState::default(),
         })
     }
+
+    /// This block in `state`, with textures and model turned by its orientation.
+    pub fn with_state(&self, state: BlockState) -> Self {
+        let orientation = state.orientation();
+
+        Self {
+            textures: orientation.orient(&self.textures),
+            model: self.model.as_ref().map(|faces| {
+                faces.iter().map(|face| face.oriented(&orientation)).collect()
+            }),
+            state,
+            ..self.clone()
+        }
+    }
 }
@@ -6,12 +6,13 @@ use bevy::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::de::from_slice as json_de;
+use std::{collections::BTreeMap, path::Path};
 use string_interner::DefaultSymbol;
 use walkdir::WalkDir;
 
 use crate::math::signed_axis::SignedAxisMap;
 
-use super::{Identifier, Interner};
+use super::{Identifier, Interner, model::BlockModel, state::BlockProperty};
 
 #[derive(Deserialize, Serialize)]
 struct BlockLibConfig {
@@ -24,6 +25,7 @@ pub struct IntermediateBlockLib {
     pub interner: Interner,
     pub blocks: Vec<(Identifier, Handle<IntermediateBlock>)>,
     pub textures: Vec<(Identifier, Handle<Image>)>,
+    pub tags: Vec<(Identifier, Handle<IntermediateTag>)>,
     pub texture_size: UVec2,
 }
 
@@ -55,6 +57,7 @@ impl AssetLoader for IntermediateBlockLibLoader {
 
             let mut blocks = Vec::new();
             let mut textures = Vec::new();
+            let mut tags = Vec::new();
 
             let mut interner = Interner::new();
 
@@ -63,6 +66,7 @@ impl AssetLoader for IntermediateBlockLibLoader {
 
                 let blocks_path = format!("block_libs/{lib_name}/blocks");
                 let textures_path = format!("block_libs/{lib_name}/textures");
+                let tags_path = format!("block_libs/{lib_name}/tags");
 
                 push_names_and_handles(
                     namespace,
@@ -78,11 +82,23 @@ impl AssetLoader for IntermediateBlockLibLoader {
                     load_context,
                     &mut interner,
                 );
+
+                // tags are optional
+                if Path::new(&tags_path).is_dir() {
+                    push_names_and_handles(
+                        namespace,
+                        &mut tags,
+                        &tags_path,
+                        load_context,
+                        &mut interner,
+                    );
+                }
             }
 
             Ok(IntermediateBlockLib {
                 blocks,
                 textures,
+                tags,
                 texture_size,
                 interner,
             })
@@ -95,7 +111,27 @@ pub struct IntermediateBlock {
     pub display_name: String,
     pub collision_aabbs: Vec<Aabb3d>,
     pub is_transparent: bool,
+    /// Whether faces between two of this block are hidden, like glass but not leaves.
+    #[serde(default = "connects_to_same_default")]
+    pub connects_to_same: bool,
+    /// Block light emitted, `0..=15`.
+    #[serde(default)]
+    pub emission: u8,
+    /// Light lost passing through, `0..=15`. Defaults to `15` for opaque
+    /// cubes and `0` for transparent blocks and other models.
+    #[serde(default)]
+    pub light_opacity: Option<u8>,
+    #[serde(default)]
+    pub model: BlockModel,
+    /// Textures of the default state, see `state` for orientation.
     pub textures: SignedAxisMap<String>,
+    /// Properties by name, each combination of values is its own state.
+    #[serde(default)]
+    pub properties: BTreeMap<String, BlockProperty>,
+}
+
+fn connects_to_same_default() -> bool {
+    true
 }
 
 #[derive(Debug, Default)]
@@ -126,6 +162,46 @@ impl AssetLoader for IntermediateBlockLoader {
     }
 }
 
+/// Blocks and other tags in a tag, as `namespace:name`.
+///
+/// A block includes all of its states unless one is given as
+/// `namespace:name[property=value,..]`.
+#[derive(Debug, Serialize, Deserialize, Asset, TypePath, Clone)]
+pub struct IntermediateTag {
+    #[serde(default)]
+    pub blocks: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct IntermediateTagLoader;
+
+impl AssetLoader for IntermediateTagLoader {
+    type Asset = IntermediateTag;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+
+    fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _: &Self::Settings,
+        _: &mut LoadContext,
+    ) -> impl ConditionalSendFuture<Output = Result<Self::Asset, Self::Error>>
+    {
+        async move {
+            let mut buffer = Vec::new();
+            reader.read_to_end(&mut buffer).await?;
+
+            Ok(json_de(&buffer)?)
+        }
+    }
+}
+
 #[inline]
 fn push_names_and_handles<A: Asset>(
     namespace: DefaultSymbol,
@@ -1,18 +1,31 @@
 pub mod block;
 mod intermediate;
+pub mod model;
+pub mod reload;
+pub mod remap;
+pub mod state;
+pub mod tags;
 mod texture_array;
 
 use bevy::{platform::collections::HashMap, prelude::*};
 pub use block::Block;
-use intermediate::{IntermediateBlock, IntermediateBlockLib};
-use std::{ops::Index, sync::Arc};
+use intermediate::{
+    IntermediateBlock, IntermediateBlockLib, IntermediateBlockLibLoader, IntermediateBlockLoader,
+    IntermediateTag, IntermediateTagLoader,
+};
+use state::BlockState;
+use std::{
+    ops::{Index, Range},
+    sync::Arc,
+};
+use tags::BlockTag;
 use string_interner::{DefaultSymbol, StringInterner, backend::BufferBackend};
 
-use crate::voxel::Voxel;
+use crate::chunk::VoxelIndex;
 
 pub type Interner = StringInterner<BufferBackend>;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Identifier {
     pub namespace: DefaultSymbol,
     pub name: DefaultSymbol,
@@ -22,10 +35,15 @@ pub struct Identifier {
 pub struct BlockLibrary(pub Arc<InnerBlockLibrary>);
 
 pub struct InnerBlockLibrary {
+    /// Every state of every block, the states of a block contiguous.
     pub blocks: Vec<Block>,
     pub identifiers: Vec<Identifier>,
+    /// Index of the default state of each block.
     pub blocks_map: HashMap<Identifier, usize>,
     pub interner: Interner,
+    /// One layer per texture, indexed by `Block::textures`.
+    pub texture_array: Handle<Image>,
+    pub tags: HashMap<Identifier, BlockTag>,
 }
 
 impl InnerBlockLibrary {
@@ -33,11 +51,13 @@ impl InnerBlockLibrary {
         intermediate: &IntermediateBlockLib,
         image_assets: ResMut<Assets<Image>>,
         block_assets: Res<Assets<IntermediateBlock>>,
+        tag_assets: Res<Assets<IntermediateTag>>,
         // material_assets: ResMut<Assets<TextureArrayMaterial>>,
     ) -> Self {
         let IntermediateBlockLib {
             blocks: intermediate_blocks,
             textures,
+            tags: intermediate_tags,
             texture_size,
             interner,
         } = intermediate;
@@ -59,34 +79,177 @@ impl InnerBlockLibrary {
                 continue;
             };
 
-            let index = blocks.len();
+            let states = BlockState::expand(&intermediate.properties);
+            if states.is_empty() {
+                error!("IntermediateBlock {identifier:?} has a property without values");
+                continue;
+            }
+
+            blocks_map.insert(*identifier, blocks.len());
+
+            for state in states {
+                blocks.push(block.with_state(state));
+                identifiers.push(*identifier);
+            }
+        }
+
+        let mut library = Self {
+            blocks,
+            identifiers,
+            blocks_map,
+            interner: interner.clone(),
+            texture_array: image,
+            tags: HashMap::new(),
+        };
+
+        let intermediate_tags = intermediate_tags
+            .iter()
+            .map(|(identifier, handle)| (*identifier, tag_assets.get(handle).unwrap()))
+            .collect::<Vec<_>>();
+
+        library.tags = tags::resolve(&library, &intermediate_tags);
+
+        library
+    }
+}
+
+impl InnerBlockLibrary {
+    /// Builds a library from `namespace:name` and `Block` pairs, without
+    /// loading assets or a texture array. Panics if an identifier has no `:`.
+    pub fn from_blocks<'a>(entries: impl IntoIterator<Item = (&'a str, Block)>) -> Self {
+        let mut interner = Interner::new();
+        let mut blocks = Vec::new();
+        let mut identifiers = Vec::new();
+        let mut blocks_map = HashMap::new();
+
+        for (string, block) in entries {
+            let (namespace, name) = string
+                .split_once(':')
+                .expect("identifier should be `namespace:name`");
+
+            let identifier = Identifier {
+                namespace: interner.get_or_intern(namespace),
+                name: interner.get_or_intern(name),
+            };
 
+            blocks_map.insert(identifier, blocks.len());
             blocks.push(block);
-            identifiers.push(*identifier);
-            blocks_map.insert(*identifier, index);
+            identifiers.push(identifier);
         }
 
         Self {
             blocks,
             identifiers,
             blocks_map,
-            interner: interner.clone(),
+            interner,
+            texture_array: Handle::default(),
+            tags: HashMap::new(),
+        }
+    }
+
+    pub fn voxel_index(&self, identifier: &Identifier) -> Option<VoxelIndex> {
+        self.blocks_map
+            .get(identifier)
+            .copied()
+            .and_then(VoxelIndex::new)
+    }
+
+    /// Parses `namespace:name` without interning new strings.
+    pub fn parse_identifier(&self, string: &str) -> Option<Identifier> {
+        let (namespace, name) = string.split_once(':')?;
+
+        Some(Identifier {
+            namespace: self.interner.get(namespace)?,
+            name: self.interner.get(name)?,
+        })
+    }
+
+    /// Looks up `namespace:name`, its default state, or a state of it as
+    /// `namespace:name[property=value,..]`.
+    pub fn lookup(&self, string: &str) -> Option<VoxelIndex> {
+        let Some((identifier, state)) = string.split_once('[') else {
+            return self.voxel_index(&self.parse_identifier(string)?);
+        };
+
+        let state = state.strip_suffix(']')?;
+        let identifier = self.parse_identifier(identifier)?;
+
+        self.state_indices(&identifier)?
+            .find(|&index| self.blocks[index].state.matches(state))
+            .and_then(VoxelIndex::new)
+    }
+
+    /// Indices of every state of `identifier`, the default first.
+    pub fn state_indices(&self, identifier: &Identifier) -> Option<Range<usize>> {
+        let default = *self.blocks_map.get(identifier)?;
+        let end = (default..self.blocks.len())
+            .find(|&index| self.identifiers[index] != *identifier)
+            .unwrap_or(self.blocks.len());
+
+        Some(default..end)
+    }
+
+    /// The tag `namespace:name`, see `tags.rs`.
+    pub fn tag(&self, string: &str) -> Option<&BlockTag> {
+        self.tags.get(&self.parse_identifier(string)?)
+    }
+
+    /// Whether `voxel` has the tag `namespace:name`, `false` if there is
+    /// no such tag. Hot paths should keep the `BlockTag` from `tag`.
+    pub fn has_tag(&self, voxel: VoxelIndex, tag: &str) -> bool {
+        self.tag(tag).is_some_and(|tag| tag.contains(voxel))
+    }
+
+    /// Formats the `Identifier` and state of `voxel` as `namespace:name`
+    /// or `namespace:name[property=value,..]`.
+    pub fn identifier_string(&self, voxel: VoxelIndex) -> String {
+        let Identifier { namespace, name } = self.identifiers[voxel.get()];
+
+        let namespace = self.interner.resolve(namespace).unwrap();
+        let name = self.interner.resolve(name).unwrap();
+
+        let state = &self.blocks[voxel.get()].state;
+        if state.is_empty() {
+            format!("{namespace}:{name}")
+        } else {
+            format!("{namespace}:{name}[{state}]")
         }
     }
 }
 
-impl Index<Voxel> for InnerBlockLibrary {
+impl Index<VoxelIndex> for InnerBlockLibrary {
     type Output = Block;
 
-    fn index(&self, index: Voxel) -> &Self::Output {
+    fn index(&self, index: VoxelIndex) -> &Self::Output {
         &self.blocks[index.0.get() as usize]
     }
 }
 
-impl Index<Name> for InnerBlockLibrary {
+impl Index<Identifier> for InnerBlockLibrary {
     type Output = Block;
 
-    fn index(&self, index: Name) -> &Self::Output {
+    fn index(&self, index: Identifier) -> &Self::Output {
         &self.blocks[*self.blocks_map.get(&index).unwrap()]
     }
 }
+
+pub struct BlockLibPlugin;
+
+impl Plugin for BlockLibPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<IntermediateBlockLib>()
+            .init_asset::<IntermediateBlock>()
+            .init_asset::<IntermediateTag>()
+            .register_asset_loader(IntermediateBlockLibLoader)
+            .init_asset_loader::<IntermediateBlockLoader>()
+            .init_asset_loader::<IntermediateTagLoader>()
+            .add_systems(
+                PreUpdate,
+                (
+                    reload::rebuild_block_library,
+                    remap::remap_chunk_maps.run_if(resource_exists::<BlockLibrary>),
+                )
+                    .chain(),
+            );
+    }
+}
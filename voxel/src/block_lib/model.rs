@@ -0,0 +1,189 @@
+use bevy::math::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::math::{sign::*, signed_axis::*};
+
+use super::state::Orientation;
+
+// Models are authored in sixteenths of a voxel and resolved into
+// `ModelFace`s relative to the minimum corner of the voxel. Cubes have no
+// faces and go through the greedy `Mesher`, every other model through the
+// `ModelMesher`.
+//
+// Model voxels are neither opaque nor transparent in the chunk masks, so
+// they never cull their neighbours. Their own faces on the side of the
+// voxel are culled by an opaque neighbour on that side.
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockModel {
+    #[default]
+    Cube,
+    /// Lower half of a cube.
+    Slab,
+    /// A slab with a step on its `pos_z` half.
+    Stairs,
+    /// Two diagonal planes seen from both sides, textured with `pos_z`.
+    Cross,
+    Boxes {
+        boxes: Vec<ModelBox>,
+    },
+}
+
+impl BlockModel {
+    #[inline]
+    pub fn is_cube(&self) -> bool {
+        matches!(self, Self::Cube)
+    }
+
+    /// Faces textured by `textures`, `None` for `Cube`.
+    pub fn faces(&self, textures: &SignedAxisMap<u32>) -> Option<Vec<ModelFace>> {
+        let boxes = match self {
+            Self::Cube => return None,
+            Self::Slab => vec![ModelBox::new([0.0; 3], [16.0, 8.0, 16.0], Vec::new())],
+            Self::Stairs => vec![
+                ModelBox::new([0.0; 3], [16.0, 8.0, 16.0], Vec::new()),
+                ModelBox::new([0.0, 8.0, 8.0], [16.0; 3], vec![NegY]),
+            ],
+            Self::Cross => return Some(cross(textures[PosZ])),
+            Self::Boxes { boxes } => boxes.clone(),
+        };
+
+        Some(
+            boxes
+                .iter()
+                .flat_map(|model_box| model_box.faces(textures))
+                .collect(),
+        )
+    }
+}
+
+/// A box from `from` to `to` in sixteenths of a voxel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelBox {
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+    /// `[min_u, min_v, max_u, max_v]` in sixteenths per face, defaults to
+    /// the box projected onto the face.
+    #[serde(default)]
+    pub uvs: HashMap<SignedAxis, [f32; 4]>,
+    /// Faces left out, like those against another box.
+    #[serde(default)]
+    pub hidden: Vec<SignedAxis>,
+}
+
+impl ModelBox {
+    fn new(from: [f32; 3], to: [f32; 3], hidden: Vec<SignedAxis>) -> Self {
+        Self {
+            from,
+            to,
+            uvs: HashMap::new(),
+            hidden,
+        }
+    }
+
+    fn faces(&self, textures: &SignedAxisMap<u32>) -> impl Iterator<Item = ModelFace> {
+        let min = Vec3::from_array(self.from) / 16.0;
+        let max = Vec3::from_array(self.to) / 16.0;
+
+        SignedAxis::ALL
+            .into_iter()
+            .filter(|signed_axis| !self.hidden.contains(signed_axis))
+            .map(move |signed_axis| {
+                // `u` cross `v` points out of the box
+                let (origin, u, v) = match signed_axis {
+                    PosX => (Vec3::new(max.x, min.y, max.z), Vec3::NEG_Z, Vec3::Y),
+                    NegX => (min, Vec3::Z, Vec3::Y),
+                    PosY => (Vec3::new(min.x, max.y, max.z), Vec3::X, Vec3::NEG_Z),
+                    NegY => (min, Vec3::X, Vec3::Z),
+                    PosZ => (Vec3::new(min.x, min.y, max.z), Vec3::X, Vec3::Y),
+                    NegZ => (Vec3::new(max.x, min.y, min.z), Vec3::NEG_X, Vec3::Y),
+                };
+
+                let size = max - min;
+                let (w, h) = (size.dot(u.abs()), size.dot(v.abs()));
+
+                let uv = self.uvs.get(&signed_axis).copied().unwrap_or_else(|| {
+                    let (su, sv) = (start(origin, u), start(origin, v));
+                    let (w, h) = (w * 16.0, h * 16.0);
+                    // texture `v` grows downward
+                    [su, 16.0 - sv - h, su + w, 16.0 - sv]
+                });
+
+                let (sign, axis) = signed_axis.components();
+                let side = match sign {
+                    Pos => max[axis as usize] >= 1.0,
+                    Neg => min[axis as usize] <= 0.0,
+                };
+
+                ModelFace {
+                    origin,
+                    u: u * w,
+                    v: v * h,
+                    uv: uv.map(|c| c.round().clamp(0.0, 16.0) as u8),
+                    texture_index: textures[signed_axis],
+                    cull: side.then_some(signed_axis),
+                }
+            })
+    }
+}
+
+/// Distance in sixteenths from the side of the voxel `direction` runs
+/// from to `corner`.
+#[inline]
+fn start(corner: Vec3, direction: Vec3) -> f32 {
+    let distance = corner.dot(direction.abs()) * 16.0;
+    if direction.max_element() > 0.0 {
+        distance
+    } else {
+        16.0 - distance
+    }
+}
+
+fn cross(texture_index: u32) -> Vec<ModelFace> {
+    [
+        (Vec3::ZERO, Vec3::new(1.0, 0.0, 1.0)),
+        (Vec3::Z, Vec3::new(1.0, 0.0, -1.0)),
+    ]
+    .into_iter()
+    .flat_map(|(origin, u)| {
+        // the back runs the other way to face out
+        [(origin, u), (origin + u, -u)].map(|(origin, u)| ModelFace {
+            origin,
+            u,
+            v: Vec3::Y,
+            uv: [0, 0, 16, 16],
+            texture_index,
+            cull: None,
+        })
+    })
+    .collect()
+}
+
+/// A quad of a resolved model, relative to the minimum corner of its voxel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelFace {
+    /// Corner at `[min_u, max_v]`, the quad spans `u` and `v` from it.
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    /// `[min_u, min_v, max_u, max_v]` in sixteenths.
+    pub uv: [u8; 4],
+    pub texture_index: u32,
+    /// Side of the voxel the face lies on, where it is culled and lit
+    /// from. `None` for faces inside the voxel, lit from the voxel itself.
+    pub cull: Option<SignedAxis>,
+}
+
+impl ModelFace {
+    pub fn oriented(&self, orientation: &Orientation) -> Self {
+        Self {
+            origin: orientation.rotate_point(self.origin),
+            u: orientation.rotate_vector(self.u),
+            v: orientation.rotate_vector(self.v),
+            cull: self.cull.map(|signed_axis| orientation.apply(signed_axis)),
+            ..*self
+        }
+    }
+}
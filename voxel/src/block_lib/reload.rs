@@ -0,0 +1,101 @@
+use bevy::{asset::AssetPath, prelude::*};
+use std::sync::Arc;
+
+use super::{
+    BlockLibrary, InnerBlockLibrary,
+    intermediate::{IntermediateBlock, IntermediateBlockLib, IntermediateTag},
+};
+
+// The `BlockLibrary` is built once the `BlockLibrarySource` and every
+// block, texture and tag it lists have loaded, then rebuilt whenever any
+// of them is modified. Replacing the resource makes `remap_chunk_maps` move
+// loaded chunks to the new indices and remesh them. Modifications are
+// only seen with the `hot_reload` feature, which watches asset files.
+
+/// The block library config to build the `BlockLibrary` from.
+#[derive(Resource, Debug, Clone)]
+pub struct BlockLibrarySource(Handle<IntermediateBlockLib>);
+
+impl BlockLibrarySource {
+    pub fn load<'a>(asset_server: &AssetServer, path: impl Into<AssetPath<'a>>) -> Self {
+        Self(asset_server.load(path))
+    }
+}
+
+pub fn rebuild_block_library(
+    mut commands: Commands,
+    source: Option<Res<BlockLibrarySource>>,
+    asset_server: Res<AssetServer>,
+    mut lib_events: MessageReader<AssetEvent<IntermediateBlockLib>>,
+    mut block_events: MessageReader<AssetEvent<IntermediateBlock>>,
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    mut tag_events: MessageReader<AssetEvent<IntermediateTag>>,
+    mut pending: Local<bool>,
+    lib_assets: Res<Assets<IntermediateBlockLib>>,
+    block_assets: Res<Assets<IntermediateBlock>>,
+    tag_assets: Res<Assets<IntermediateTag>>,
+    image_assets: ResMut<Assets<Image>>,
+) {
+    let Some(BlockLibrarySource(handle)) = source.as_deref() else {
+        return;
+    };
+
+    *pending |= lib_events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(handle) || event.is_modified(handle));
+
+    let modified_blocks = block_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let modified_images = image_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let modified_tags = tag_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let Some(intermediate) = lib_assets.get(handle) else {
+        return;
+    };
+
+    *pending |= intermediate
+        .blocks
+        .iter()
+        .any(|(_, block)| modified_blocks.contains(&block.id()));
+
+    *pending |= intermediate
+        .textures
+        .iter()
+        .any(|(_, image)| modified_images.contains(&image.id()));
+
+    *pending |= intermediate
+        .tags
+        .iter()
+        .any(|(_, tag)| modified_tags.contains(&tag.id()));
+
+    // a modified block, texture or tag may still be reloading
+    if !*pending || !asset_server.is_loaded_with_dependencies(handle) {
+        return;
+    }
+
+    *pending = false;
+
+    let inner = InnerBlockLibrary::build(intermediate, image_assets, block_assets, tag_assets);
+    info!("Built BlockLibrary with {} blocks", inner.blocks.len());
+
+    commands.insert_resource(BlockLibrary(Arc::new(inner)));
+}
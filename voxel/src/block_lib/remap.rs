@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+
+use crate::{
+    chunk::{
+        ChunkMap, DirtyChunks, GenerationTasks, LightMap, LightUpdates, VoxelIndex,
+        generator::{PendingWrites, TerrainGenerator},
+    },
+    edit::EditedChunks,
+    history::EditHistory,
+};
+
+use super::{BlockLibrary, InnerBlockLibrary};
+
+/// Maps each `VoxelIndex` of one `BlockLibrary` to the block with the
+/// same `namespace:name` and state in another, or `None` if it was removed.
+/// States that no longer exist map to the default state.
+#[derive(Debug, Clone)]
+pub struct BlockRemap(Vec<Option<VoxelIndex>>);
+
+impl BlockRemap {
+    pub fn between(old: &InnerBlockLibrary, new: &InnerBlockLibrary) -> Self {
+        let remap = (0..old.blocks.len())
+            .map(|index| {
+                let voxel = VoxelIndex::new(index)?;
+                let string = old.identifier_string(voxel);
+
+                new.lookup(&string).or_else(|| {
+                    let (identifier, _) = string.split_once('[')?;
+                    new.lookup(identifier)
+                })
+            })
+            .collect();
+
+        Self(remap)
+    }
+
+    #[inline]
+    pub fn get(&self, voxel_opt: Option<VoxelIndex>) -> Option<VoxelIndex> {
+        self.0.get(voxel_opt?.get()).copied().flatten()
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.0
+            .iter()
+            .enumerate()
+            .all(|(index, voxel_opt)| voxel_opt.is_some_and(|v| v.get() == index))
+    }
+}
+
+/// Remaps every loaded `ChunkMap` when the `BlockLibrary` resource is replaced,
+/// then relights and remeshes it. Generation in flight is restarted with
+/// the new library and everything else holding voxels of a terrain remapped.
+pub fn remap_chunk_maps(
+    block_library: Res<BlockLibrary>,
+    mut previous: Local<Option<BlockLibrary>>,
+    chunk_maps: Query<(
+        &ChunkMap,
+        &LightMap,
+        &mut LightUpdates,
+        &mut DirtyChunks,
+        &mut EditedChunks,
+        &mut EditHistory,
+        &mut GenerationTasks,
+        &PendingWrites,
+        &TerrainGenerator,
+    )>,
+) {
+    if !block_library.is_changed() {
+        return;
+    }
+
+    let Some(old) = previous.replace(BlockLibrary::clone(&block_library)) else {
+        return;
+    };
+
+    let remap = BlockRemap::between(&old, &block_library);
+
+    if !remap.is_identity() {
+        info!("BlockLibrary order changed, remapping loaded chunks");
+    }
+
+    // masks and light are rebuilt even for an identity remap since
+    // block properties may have changed
+    for (
+        chunk_map,
+        light_map,
+        mut light_updates,
+        mut dirty_chunks,
+        mut edited_chunks,
+        mut history,
+        mut generation_tasks,
+        pending_writes,
+        generator,
+    ) in chunk_maps
+    {
+        chunk_map.remap(&remap, &block_library);
+        edited_chunks.remap(&remap);
+        history.remap(&remap);
+        pending_writes.remap(&remap);
+        generation_tasks.respawn(generator, &block_library);
+
+        light_map.clear();
+        light_updates.voxels.clear();
+        light_updates.chunks.clear();
+        light_updates
+            .chunks
+            .extend(chunk_map.iter().map(|entry| *entry.key()));
+
+        dirty_chunks.extend(chunk_map.iter().map(|entry| *entry.key()));
+    }
+}
@@ -0,0 +1,248 @@
+use bevy::math::Vec3;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+use crate::math::{axis::*, axis_permutation::*, signed_axis::*};
+
+// Every combination of the properties of a block is its own `Block` with
+// its own `VoxelIndex`. States of a block are contiguous, starting with
+// the default state which takes the first value of every property.
+// Properties are ordered by name.
+//
+// Textures and models are authored for the default orientation. `facing`
+// turns the `pos_z` side toward its value and `axis` turns the `pos_y`
+// and `neg_y` sides toward the ends of its value.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockProperty {
+    /// Any `SignedAxis`, orients textures.
+    Facing,
+    /// `pos_z`, `pos_x`, `neg_z` or `neg_x`, orients textures.
+    HorizontalFacing,
+    /// `y`, `x` or `z`, orients textures.
+    Axis,
+    Bool,
+    Int {
+        min: i32,
+        max: i32,
+    },
+    Enum {
+        values: Vec<String>,
+    },
+}
+
+impl BlockProperty {
+    /// Every value, the default first.
+    pub fn values(&self) -> Vec<PropertyValue> {
+        use PropertyValue as V;
+
+        match self {
+            Self::Facing => [PosZ, PosX, NegZ, NegX, PosY, NegY].map(V::Facing).to_vec(),
+            Self::HorizontalFacing => [PosZ, PosX, NegZ, NegX].map(V::Facing).to_vec(),
+            Self::Axis => [Y, X, Z].map(V::Axis).to_vec(),
+            Self::Bool => vec![V::Bool(false), V::Bool(true)],
+            Self::Int { min, max } => (*min..=*max).map(V::Int).collect(),
+            Self::Enum { values } => values.iter().cloned().map(V::Enum).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PropertyValue {
+    Facing(SignedAxis),
+    Axis(Axis),
+    Bool(bool),
+    Int(i32),
+    Enum(String),
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Facing(signed_axis) => f.write_str(match signed_axis {
+                PosX => "pos_x",
+                NegX => "neg_x",
+                PosY => "pos_y",
+                NegY => "neg_y",
+                PosZ => "pos_z",
+                NegZ => "neg_z",
+            }),
+            Self::Axis(axis) => f.write_str(match axis {
+                X => "x",
+                Y => "y",
+                Z => "z",
+            }),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Enum(value) => f.write_str(value),
+        }
+    }
+}
+
+/// Value of every property of a block, ordered by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BlockState(pub Vec<(String, PropertyValue)>);
+
+impl BlockState {
+    /// Every combination of `properties`, the default state first.
+    pub fn expand(properties: &BTreeMap<String, BlockProperty>) -> Vec<Self> {
+        let mut states = vec![Self::default()];
+
+        for (name, property) in properties {
+            let values = property.values();
+
+            states = states
+                .into_iter()
+                .flat_map(|state| {
+                    values.iter().map(move |value| {
+                        let mut state = state.clone();
+                        state.0.push((name.clone(), value.clone()));
+                        state
+                    })
+                })
+                .collect();
+        }
+
+        states
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PropertyValue> {
+        self.0
+            .iter()
+            .find_map(|(property, value)| (property == name).then_some(value))
+    }
+
+    /// Orientation of the first `facing` or `axis` value.
+    pub fn orientation(&self) -> Orientation {
+        self.0
+            .iter()
+            .find_map(|(_, value)| match value {
+                PropertyValue::Facing(facing) => Some(Orientation::facing(*facing)),
+                PropertyValue::Axis(axis) => Some(Orientation::axis(*axis)),
+                _ => None,
+            })
+            .unwrap_or(Orientation::IDENTITY)
+    }
+
+    /// Whether `string`, `name=value` pairs separated by `,` in any order,
+    /// gives every property of this state.
+    pub fn matches(&self, string: &str) -> bool {
+        let mut count = 0;
+
+        for pair in string
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let Some((name, value)) = pair.split_once('=') else {
+                return false;
+            };
+
+            if self
+                .get(name.trim())
+                .is_none_or(|property| property.to_string() != value.trim())
+            {
+                return false;
+            }
+
+            count += 1;
+        }
+
+        count == self.0.len()
+    }
+}
+
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, value)) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Rotation taking authored faces to the faces of an oriented block.
+///
+/// Applies `AxisPermutation::axis_map` to the axis of a face after
+/// flipping its sign if `flip` is set for that axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orientation {
+    permutation: AxisPermutation,
+    flip: AxisMap<bool>,
+}
+
+impl Orientation {
+    pub const IDENTITY: Self = Self::new(XYZ, [false; 3]);
+
+    #[inline]
+    const fn new(permutation: AxisPermutation, flip: [bool; 3]) -> Self {
+        Self {
+            permutation,
+            flip: AxisMap::from_array(flip),
+        }
+    }
+
+    /// Turns `pos_z` toward `facing`.
+    pub const fn facing(facing: SignedAxis) -> Self {
+        // an odd permutation needs one flip to stay a rotation
+        match facing {
+            PosZ => Self::IDENTITY,
+            NegZ => Self::new(XYZ, [true, false, true]),
+            PosX => Self::new(ZYX, [true, false, false]),
+            NegX => Self::new(ZYX, [false, false, true]),
+            PosY => Self::new(XZY, [false, true, false]),
+            NegY => Self::new(XZY, [false, false, true]),
+        }
+    }
+
+    /// Turns the `y` axis toward `axis`.
+    pub const fn axis(axis: Axis) -> Self {
+        match axis {
+            X => Self::new(YZX, [false; 3]),
+            Y => Self::IDENTITY,
+            Z => Self::new(ZXY, [false; 3]),
+        }
+    }
+
+    #[inline]
+    pub fn apply(&self, signed_axis: SignedAxis) -> SignedAxis {
+        let (sign, axis) = signed_axis.components();
+        let sign = if self.flip[axis] { sign.flip() } else { sign };
+        SignedAxis::from_components(sign, self.permutation.axis_map()[axis])
+    }
+
+    pub fn rotate_vector(&self, vector: Vec3) -> Vec3 {
+        let axis_map = self.permutation.axis_map();
+        let mut rotated = Vec3::ZERO;
+        for axis in Axis::ALL {
+            let value = vector[axis as usize];
+            rotated[axis_map[axis] as usize] = if self.flip[axis] { -value } else { value };
+        }
+        rotated
+    }
+
+    /// Rotates `point` about the center of its voxel, relative to the
+    /// minimum corner.
+    #[inline]
+    pub fn rotate_point(&self, point: Vec3) -> Vec3 {
+        self.rotate_vector(point - 0.5) + 0.5
+    }
+
+    /// Moves each authored face value to the face it is turned toward.
+    pub fn orient<T: Copy>(&self, authored: &SignedAxisMap<T>) -> SignedAxisMap<T> {
+        let mut oriented = *authored;
+        for signed_axis in SignedAxis::ALL {
+            oriented[self.apply(signed_axis)] = authored[signed_axis];
+        }
+        oriented
+    }
+}
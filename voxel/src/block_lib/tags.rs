@@ -0,0 +1,157 @@
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+
+use crate::chunk::VoxelIndex;
+
+use super::{Identifier, InnerBlockLibrary, intermediate::IntermediateTag};
+
+// Tags are resolved when the `InnerBlockLibrary` is built into a bitset
+// over every `VoxelIndex`, so asking whether a voxel has a tag is a
+// shift and a mask. A tag holds the blocks of every tag it lists. Cycles
+// and unknown names are skipped with a warning.
+
+/// Every `VoxelIndex` with a tag.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockTag(Vec<u64>);
+
+impl BlockTag {
+    fn with_len(len: usize) -> Self {
+        Self(vec![0; len.div_ceil(64)])
+    }
+
+    #[inline]
+    pub fn contains(&self, voxel: VoxelIndex) -> bool {
+        let index = voxel.get();
+        self.0
+            .get(index / 64)
+            .is_some_and(|word| (word >> (index % 64)) & 1 != 0)
+    }
+
+    #[inline]
+    fn insert(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    fn union(&mut self, other: &Self) {
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word |= other;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = VoxelIndex> {
+        self.0.iter().enumerate().flat_map(|(word_index, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                VoxelIndex::new(word_index * 64 + bit)
+            })
+        })
+    }
+}
+
+/// Resolves `tags` against the blocks of `library`.
+pub(super) fn resolve(
+    library: &InnerBlockLibrary,
+    tags: &[(Identifier, &IntermediateTag)],
+) -> HashMap<Identifier, BlockTag> {
+    let mut resolver = Resolver {
+        library,
+        sources: tags.iter().copied().collect(),
+        resolved: HashMap::new(),
+        resolving: HashSet::new(),
+    };
+
+    for (identifier, _) in tags {
+        resolver.resolve(*identifier);
+    }
+
+    resolver.resolved
+}
+
+struct Resolver<'a> {
+    library: &'a InnerBlockLibrary,
+    sources: HashMap<Identifier, &'a IntermediateTag>,
+    resolved: HashMap<Identifier, BlockTag>,
+    /// Tags being resolved, to break cycles.
+    resolving: HashSet<Identifier>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, identifier: Identifier) -> BlockTag {
+        if let Some(tag) = self.resolved.get(&identifier) {
+            return tag.clone();
+        }
+
+        let mut tag = BlockTag::with_len(self.library.blocks.len());
+        let source = self.sources[&identifier];
+        let name = self.name(identifier);
+
+        if !self.resolving.insert(identifier) {
+            warn!("Tag {name} includes itself, skipping");
+            return tag;
+        }
+
+        for string in &source.blocks {
+            // a state or every state of a block
+            let indices = if string.contains('[') {
+                self.library
+                    .lookup(string)
+                    .map(|voxel| voxel.get()..voxel.get() + 1)
+            } else {
+                self.library
+                    .parse_identifier(string)
+                    .and_then(|identifier| self.library.state_indices(&identifier))
+            };
+
+            let Some(indices) = indices else {
+                warn!("Tag {name} lists unknown block {string}");
+                continue;
+            };
+
+            for index in indices {
+                tag.insert(index);
+            }
+        }
+
+        for string in &source.tags {
+            let Some(included) = self
+                .library
+                .parse_identifier(string)
+                .filter(|included| self.sources.contains_key(included))
+            else {
+                warn!("Tag {name} lists unknown tag {string}");
+                continue;
+            };
+
+            let included = self.resolve(included);
+            tag.union(&included);
+        }
+
+        self.resolving.remove(&identifier);
+        self.resolved.insert(identifier, tag.clone());
+
+        tag
+    }
+
+    fn name(&self, Identifier { namespace, name }: Identifier) -> String {
+        let interner = &self.library.interner;
+        let namespace = interner.resolve(namespace).unwrap();
+        let name = interner.resolve(name).unwrap();
+
+        format!("{namespace}:{name}")
+    }
+}
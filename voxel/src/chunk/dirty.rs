@@ -0,0 +1,73 @@
+use bevy::{
+    platform::collections::HashSet,
+    prelude::*,
+    render::renderer::{RenderDevice, RenderQueue},
+};
+
+use crate::{block_lib::BlockLibrary, render::alloc_buffer::AllocBuffer};
+
+use super::{
+    ChunkMap, ChunkPos, LightMap, MeshingSettings, MeshingTasks, ModelQuad, VoxelQuad,
+    padded_chunks,
+};
+
+// Edits mark every chunk they change, padding included, and
+// `remesh_dirty_chunks` spawns at most one meshing task per chunk
+// however many edits it received. Chunks still being meshed stay
+// dirty until their task finishes so a stale mesh can't land last.
+
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct DirtyChunks(pub HashSet<ChunkPos>);
+
+impl DirtyChunks {
+    #[inline]
+    pub fn mark(&mut self, chunk_pos: ChunkPos) {
+        self.insert(chunk_pos);
+    }
+
+    /// Marks every chunk whose padded volume contains `voxel_pos`.
+    pub fn mark_voxel(&mut self, voxel_pos: IVec3) {
+        self.extend(padded_chunks(voxel_pos).map(|(chunk_pos, _)| chunk_pos));
+    }
+}
+
+pub fn remesh_dirty_chunks(
+    block_library: Res<BlockLibrary>,
+    settings: Res<MeshingSettings>,
+    render: Option<(
+        Res<AllocBuffer<VoxelQuad>>,
+        Res<AllocBuffer<ModelQuad>>,
+        Res<RenderQueue>,
+        Res<RenderDevice>,
+    )>,
+    terrains: Query<(&ChunkMap, &LightMap, &mut DirtyChunks, &mut MeshingTasks)>,
+) {
+    for (chunk_map, light_map, mut dirty_chunks, mut meshing_tasks) in terrains {
+        if settings.is_changed() {
+            dirty_chunks.extend(chunk_map.iter().map(|entry| *entry.key()));
+        }
+
+        let Some((alloc_buffer, model_buffer, queue, device)) = &render else {
+            dirty_chunks.clear();
+            continue;
+        };
+
+        dirty_chunks.retain(|chunk_pos| {
+            if !chunk_map.contains_key(chunk_pos) {
+                return false;
+            }
+
+            !meshing_tasks.spawn_task(
+                chunk_map.clone(),
+                light_map.clone(),
+                *chunk_pos,
+                AllocBuffer::clone(alloc_buffer),
+                AllocBuffer::clone(model_buffer),
+                BlockLibrary::clone(&block_library),
+                *settings,
+                RenderQueue::clone(queue),
+                RenderDevice::clone(device),
+            )
+        });
+    }
+}
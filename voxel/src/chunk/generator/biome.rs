@@ -0,0 +1,227 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+    tasks::ConditionalSendFuture,
+};
+use fastnoise_lite::FastNoiseLite;
+use serde::{Deserialize, Serialize};
+use serde_json::de::from_slice as json_de;
+
+use crate::{
+    block_lib::BlockLibrary,
+    chunk::pad::{AREA, LEN},
+};
+
+use super::{
+    Chunk, ChunkGenerator, ChunkPos, VoxelIndex, chunk_origin, from_fn, lookup_block,
+    noise::{HEIGHT_SEED, HUMIDITY_SEED, TEMPERATURE_SEED, noise_seed},
+};
+
+// Two low frequency noise fields, temperature and humidity, place every
+// column in climate space. Each biome sits at a point in that space and
+// is weighted by its distance to the column, so heights blend smoothly
+// across borders while blocks come from the heaviest biome.
+
+#[derive(Debug, Clone, Serialize, Deserialize, Asset, TypePath)]
+pub struct Biome {
+    pub display_name: String,
+    /// Climate space position, both in `-1.0..=1.0`.
+    pub temperature: f32,
+    pub humidity: f32,
+    /// Blocks by `namespace:name`.
+    pub surface: String,
+    pub subsurface: String,
+    pub filler: String,
+    pub subsurface_depth: u32,
+    pub height: HeightCurve,
+}
+
+/// Maps height noise in `-1.0..=1.0` to a height in voxels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeightCurve {
+    pub frequency: f32,
+    /// `[noise, height]` control points sorted by noise, linearly interpolated.
+    pub points: Vec<[f32; 2]>,
+}
+
+impl HeightCurve {
+    pub fn sample(&self, noise: f32) -> f32 {
+        let Some(first) = self.points.first() else {
+            return 0.0;
+        };
+
+        if noise <= first[0] {
+            return first[1];
+        }
+
+        for pair in self.points.windows(2) {
+            let [[n0, h0], [n1, h1]] = [pair[0], pair[1]];
+
+            if noise <= n1 {
+                let t = (noise - n0) / (n1 - n0).max(f32::EPSILON);
+                return h0 + (h1 - h0) * t;
+            }
+        }
+
+        self.points.last().unwrap()[1]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BiomeGenerator {
+    pub biomes: Vec<Biome>,
+    pub climate_frequency: f32,
+    /// Larger values make borders between biomes narrower.
+    pub blend_sharpness: f32,
+}
+
+impl BiomeGenerator {
+    pub fn new(biomes: Vec<Biome>) -> Self {
+        Self {
+            biomes,
+            climate_frequency: 0.001,
+            blend_sharpness: 16.0,
+        }
+    }
+}
+
+struct Column {
+    height: i32,
+    biome: usize,
+}
+
+struct BiomeBlocks {
+    surface: Option<VoxelIndex>,
+    subsurface: Option<VoxelIndex>,
+    filler: Option<VoxelIndex>,
+    subsurface_depth: i32,
+}
+
+impl ChunkGenerator for BiomeGenerator {
+    fn generate(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk {
+        if self.biomes.is_empty() {
+            return Chunk::EMPTY;
+        }
+
+        let seed = noise_seed(seed);
+
+        let mut temperature = FastNoiseLite::with_seed(seed.wrapping_add(TEMPERATURE_SEED));
+        temperature.set_frequency(Some(self.climate_frequency));
+
+        let mut humidity = FastNoiseLite::with_seed(seed.wrapping_add(HUMIDITY_SEED));
+        humidity.set_frequency(Some(self.climate_frequency));
+
+        let heights = self
+            .biomes
+            .iter()
+            .enumerate()
+            .map(|(index, biome)| {
+                let offset = HEIGHT_SEED.wrapping_add(index as i32);
+                let mut noise = FastNoiseLite::with_seed(seed.wrapping_add(offset));
+                noise.set_frequency(Some(biome.height.frequency));
+                noise
+            })
+            .collect::<Vec<_>>();
+
+        let blocks = self
+            .biomes
+            .iter()
+            .map(|biome| BiomeBlocks {
+                surface: lookup_block(block_library, &biome.surface),
+                subsurface: lookup_block(block_library, &biome.subsurface),
+                filler: lookup_block(block_library, &biome.filler),
+                subsurface_depth: biome.subsurface_depth as i32,
+            })
+            .collect::<Vec<_>>();
+
+        let chunk_origin = chunk_origin(chunk_pos);
+
+        let mut weights = vec![0.0; self.biomes.len()];
+
+        let columns = (0..AREA)
+            .map(|index| {
+                let x = (chunk_origin.x + (index % LEN) as i32) as f32;
+                let z = (chunk_origin.z + (index / LEN) as i32) as f32;
+
+                let climate =
+                    Vec2::new(temperature.get_noise_2d(x, z), humidity.get_noise_2d(x, z));
+
+                for (weight, biome) in weights.iter_mut().zip(&self.biomes) {
+                    let distance_sq =
+                        climate.distance_squared(Vec2::new(biome.temperature, biome.humidity));
+                    *weight = (-distance_sq * self.blend_sharpness).exp();
+                }
+
+                let total = weights.iter().sum::<f32>().max(f32::EPSILON);
+
+                let height = self
+                    .biomes
+                    .iter()
+                    .zip(&heights)
+                    .zip(&weights)
+                    .map(|((biome, noise), weight)| {
+                        biome.height.sample(noise.get_noise_2d(x, z)) * weight
+                    })
+                    .sum::<f32>()
+                    / total;
+
+                let biome = weights
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(index, _)| index)
+                    .unwrap();
+
+                Column {
+                    height: height.floor() as i32,
+                    biome,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        from_fn(chunk_pos, block_library, |voxel_pos| {
+            let offset = voxel_pos - chunk_origin;
+            let Column { height, biome } = columns[offset.x as usize + offset.z as usize * LEN];
+
+            let depth = height - voxel_pos.y;
+            let blocks = &blocks[biome];
+
+            if depth < 0 {
+                None
+            } else if depth == 0 {
+                blocks.surface
+            } else if depth <= blocks.subsurface_depth {
+                blocks.subsurface
+            } else {
+                blocks.filler
+            }
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BiomeLoader;
+
+impl AssetLoader for BiomeLoader {
+    type Asset = Biome;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    fn extensions(&self) -> &[&str] {
+        &["biome.json"]
+    }
+
+    fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _: &Self::Settings,
+        _: &mut LoadContext,
+    ) -> impl ConditionalSendFuture<Output = Result<Self::Asset, Self::Error>> {
+        async move {
+            let mut buffer = Vec::new();
+            reader.read_to_end(&mut buffer).await?;
+
+            Ok(json_de(&buffer)?)
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use dashmap::{DashMap, DashSet};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::sync::Arc;
+
+use crate::{
+    block_lib::{BlockLibrary, remap::BlockRemap},
+    chunk::{ChunkMap, DirtyChunks, GenerationTasks, LightUpdates, pad, padded_chunks, unpad},
+    edit::EditedChunks,
+};
+
+use super::{Chunk, ChunkGenerator, ChunkPos, VoxelIndex, chunk_origin};
+
+// Decoration runs after the base generator. Each `Feature` places what
+// originates inside the chunk, using an rng seeded from the world seed and
+// `ChunkPos` so the result doesn't depend on generation order.
+//
+// Writes that land in other chunks are kept in `PendingWrites` under the
+// chunk they spilled from until it unloads, replaced when it's generated
+// again. A chunk takes every write aimed at it when it's generated, and
+// `apply_pending_writes` delivers those spilled after it loaded. Chunks
+// that were edited or loaded from a `ChunkStorage` never take writes.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Replace,
+    /// Only writes into empty voxels.
+    IfEmpty,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PendingWrite {
+    pub pos: UVec3,
+    pub voxel_opt: Option<VoxelIndex>,
+    pub placement: Placement,
+}
+
+impl PendingWrite {
+    /// Returns `true` if the voxel changed.
+    fn apply(&self, chunk: &mut Chunk, block_library: &BlockLibrary) -> bool {
+        let old = chunk.get(self.pos);
+        if old == self.voxel_opt || (self.placement == Placement::IfEmpty && old.is_some()) {
+            return false;
+        }
+        chunk.set(self.pos, self.voxel_opt, block_library);
+        true
+    }
+}
+
+/// Applies `writes` in order, returning the positions that changed.
+fn apply_writes(
+    writes: &[PendingWrite],
+    chunk: &mut Chunk,
+    block_library: &BlockLibrary,
+) -> Vec<UVec3> {
+    writes
+        .iter()
+        .filter(|write| write.apply(chunk, block_library))
+        .map(|write| write.pos)
+        .collect()
+}
+
+/// Writes the features of one chunk spilled into others.
+#[derive(Debug, Clone, Default)]
+struct SpilledWrites {
+    targets: HashMap<ChunkPos, Vec<PendingWrite>>,
+    /// Targets that may have loaded without these writes.
+    undelivered: HashSet<ChunkPos>,
+}
+
+/// Writes waiting for their chunk by the chunk they spilled from, shared
+/// between a `DecoratedGenerator` and its terrain.
+#[derive(Component, Clone, Default)]
+pub struct PendingWrites {
+    sources: Arc<DashMap<ChunkPos, SpilledWrites>>,
+    /// Loaded chunks that came from a `ChunkStorage`.
+    stored: Arc<DashSet<ChunkPos>>,
+}
+
+impl PendingWrites {
+    /// Replaces the writes spilled by `source`.
+    pub fn spill(&self, source: ChunkPos, targets: HashMap<ChunkPos, Vec<PendingWrite>>) {
+        let undelivered = targets.keys().copied().collect();
+        self.sources.insert(
+            source,
+            SpilledWrites {
+                targets,
+                undelivered,
+            },
+        );
+    }
+
+    /// Applies every write spilled into `chunk_pos`, in order of the chunk
+    /// they spilled from, returning the positions that changed.
+    pub fn apply(
+        &self,
+        chunk_pos: ChunkPos,
+        chunk: &mut Chunk,
+        block_library: &BlockLibrary,
+    ) -> Vec<UVec3> {
+        let mut writes = self
+            .sources
+            .iter()
+            .filter_map(|entry| {
+                let writes = entry.targets.get(&chunk_pos)?.clone();
+                Some((*entry.key(), writes))
+            })
+            .collect::<Vec<_>>();
+
+        writes.sort_unstable_by_key(|(source, _)| source.to_array());
+
+        for (source, _) in &writes {
+            if let Some(mut spilled) = self.sources.get_mut(source) {
+                spilled.undelivered.remove(&chunk_pos);
+            }
+        }
+
+        writes
+            .iter()
+            .flat_map(|(_, writes)| apply_writes(writes, chunk, block_library))
+            .collect()
+    }
+
+    /// Keeps writes out of `chunk_pos`, which was loaded from a `ChunkStorage`.
+    pub fn mark_stored(&self, chunk_pos: ChunkPos) {
+        self.stored.insert(chunk_pos);
+    }
+
+    /// Drops the writes spilled by `chunk_pos` once it unloads.
+    pub fn unload(&self, chunk_pos: ChunkPos) {
+        self.sources.remove(&chunk_pos);
+        self.stored.remove(&chunk_pos);
+    }
+
+    /// Moves every write to the `BlockLibrary` `remap` maps to.
+    pub fn remap(&self, remap: &BlockRemap) {
+        for mut spilled in self.sources.iter_mut() {
+            for write in spilled.targets.values_mut().flatten() {
+                write.voxel_opt = remap.get(write.voxel_opt);
+            }
+        }
+    }
+
+    /// Chunks with spilled writes.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Writes spilled by `source` into `target`.
+    pub fn writes(&self, source: ChunkPos, target: ChunkPos) -> Vec<PendingWrite> {
+        self.sources
+            .get(&source)
+            .and_then(|spilled| spilled.targets.get(&target).cloned())
+            .unwrap_or_default()
+    }
+}
+
+pub trait Feature: Send + Sync + 'static {
+    /// Places every instance of this feature originating in `decorator.chunk_pos`.
+    fn decorate(&self, decorator: &mut Decorator);
+}
+
+pub struct Decorator<'a> {
+    pub seed: u64,
+    pub chunk_pos: ChunkPos,
+    pub rng: StdRng,
+    pub block_library: &'a BlockLibrary,
+    chunk: &'a mut Chunk,
+    spilled: HashMap<ChunkPos, Vec<PendingWrite>>,
+}
+
+impl Decorator<'_> {
+    #[inline]
+    pub fn chunk_origin(&self) -> IVec3 {
+        chunk_origin(self.chunk_pos)
+    }
+
+    /// `None` if `voxel_pos` is outside of this chunk, padding included.
+    pub fn get(&self, voxel_pos: IVec3) -> Option<Option<VoxelIndex>> {
+        let local = voxel_pos - self.chunk_origin();
+
+        let inside =
+            local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(pad::LEN as i32)).all();
+
+        inside.then(|| self.chunk.get(local.as_uvec3()))
+    }
+
+    /// Writes to every chunk containing `voxel_pos`, deferring those other than this one.
+    pub fn set(&mut self, voxel_pos: IVec3, voxel_opt: Option<VoxelIndex>, placement: Placement) {
+        for (chunk_pos, pos) in padded_chunks(voxel_pos) {
+            let write = PendingWrite {
+                pos,
+                voxel_opt,
+                placement,
+            };
+
+            if chunk_pos == self.chunk_pos {
+                write.apply(self.chunk, self.block_library);
+            } else {
+                self.spilled.entry(chunk_pos).or_default().push(write);
+            }
+        }
+    }
+
+    /// World space `x, z` of an unpadded column of this chunk.
+    pub fn random_column(&mut self) -> IVec2 {
+        let origin = self.chunk_origin();
+        let x = self.rng.random_range(1..=unpad::LEN as i32);
+        let z = self.rng.random_range(1..=unpad::LEN as i32);
+
+        IVec2::new(origin.x + x, origin.z + z)
+    }
+
+    /// Highest solid voxel in `column` with an empty voxel above it, within this chunk.
+    pub fn surface(&self, column: IVec2) -> Option<i32> {
+        let origin = self.chunk_origin();
+
+        (origin.y..origin.y + pad::LEN as i32 - 1).rev().find(|y| {
+            let below = self.get(IVec3::new(column.x, *y, column.y)).flatten();
+            let above = self.get(IVec3::new(column.x, y + 1, column.y)).flatten();
+
+            below.is_some() && above.is_none()
+        })
+    }
+}
+
+#[inline]
+pub fn chunk_rng(seed: u64, chunk_pos: ChunkPos) -> StdRng {
+    // splitmix64 over the seed and each coordinate
+    let mut hash = seed;
+    for c in chunk_pos.to_array() {
+        hash ^= c as u32 as u64;
+        hash = hash.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^= hash >> 31;
+    }
+
+    StdRng::seed_from_u64(hash)
+}
+
+/// Runs `features` over chunks produced by `base`.
+#[derive(Clone)]
+pub struct DecoratedGenerator {
+    pub base: Arc<dyn ChunkGenerator>,
+    pub features: Vec<Arc<dyn Feature>>,
+    pub pending: PendingWrites,
+}
+
+impl DecoratedGenerator {
+    pub fn new(base: impl ChunkGenerator, pending: PendingWrites) -> Self {
+        Self {
+            base: Arc::new(base),
+            features: Vec::new(),
+            pending,
+        }
+    }
+
+    pub fn with_feature(mut self, feature: impl Feature) -> Self {
+        self.features.push(Arc::new(feature));
+        self
+    }
+}
+
+impl ChunkGenerator for DecoratedGenerator {
+    fn generate(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk {
+        let mut chunk = self.base.generate(seed, chunk_pos, block_library);
+
+        let spilled = {
+            let mut decorator = Decorator {
+                seed,
+                chunk_pos,
+                rng: chunk_rng(seed, chunk_pos),
+                block_library,
+                chunk: &mut chunk,
+                spilled: HashMap::new(),
+            };
+
+            for feature in &self.features {
+                feature.decorate(&mut decorator);
+            }
+
+            decorator.spilled
+        };
+
+        self.pending.apply(chunk_pos, &mut chunk, block_library);
+        self.pending.spill(chunk_pos, spilled);
+
+        chunk.compact();
+
+        chunk
+    }
+}
+
+/// Applies writes spilled into chunks that had already loaded, then queues
+/// the voxels they changed for relighting.
+pub fn apply_pending_writes(
+    block_library: Res<BlockLibrary>,
+    terrains: Query<(
+        &ChunkMap,
+        &PendingWrites,
+        &EditedChunks,
+        &GenerationTasks,
+        &mut DirtyChunks,
+        &mut LightUpdates,
+    )>,
+) {
+    for (
+        chunk_map,
+        pending,
+        edited_chunks,
+        generation_tasks,
+        mut dirty_chunks,
+        mut light_updates,
+    ) in terrains
+    {
+        let mut deliveries = Vec::new();
+
+        for mut entry in pending.sources.iter_mut() {
+            let source = *entry.key();
+            entry.undelivered.retain(|target| {
+                if chunk_map.contains_key(target) {
+                    deliveries.push((source, *target));
+                    return false;
+                }
+
+                // generation that started before `source` spilled only sees
+                // these once it loads, later generation takes them itself
+                generation_tasks.contains(*target)
+            });
+        }
+
+        for (source, target) in deliveries {
+            if edited_chunks.contains(&target) || pending.stored.contains(&target) {
+                continue;
+            }
+
+            let writes = pending.writes(source, target);
+
+            let changed = match chunk_map.get_mut(&target) {
+                Some(mut chunk) => apply_writes(&writes, &mut chunk, &block_library),
+                None => continue,
+            };
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            dirty_chunks.mark(target);
+
+            let origin = chunk_origin(target);
+            light_updates
+                .voxels
+                .extend(changed.into_iter().map(|pos| origin + pos.as_ivec3()));
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use fastnoise_lite::{FastNoiseLite, FractalType};
+
+use crate::block_lib::BlockLibrary;
+
+use super::{
+    Chunk, ChunkGenerator, ChunkPos, from_fn, lookup_block,
+    noise::{CHEESE_SEED, SPAGHETTI_A_SEED, SPAGHETTI_B_SEED, TERRAIN_SEED, noise_seed},
+};
+
+// density = terrain_noise * amplitude - (y - base_height) * height_gradient
+//
+// A voxel is solid when density exceeds `threshold` and it is not carved
+// by a cave. Noise dominating the gradient produces overhangs and, far
+// enough above `base_height`, floating islands.
+//
+// Cheese caves are large open pockets where a low frequency noise exceeds
+// `cheese_threshold`. Spaghetti caves are tunnels along the intersection
+// of the zero surfaces of two noise fields.
+
+#[derive(Debug, Clone)]
+pub struct DensitySettings {
+    pub block: String,
+
+    pub frequency: f32,
+    pub octaves: i32,
+    pub amplitude: f32,
+    pub base_height: f32,
+    pub height_gradient: f32,
+    pub threshold: f32,
+
+    pub cheese_frequency: f32,
+    /// `1.0` or above disables cheese caves.
+    pub cheese_threshold: f32,
+
+    pub spaghetti_frequency: f32,
+    /// `0.0` disables spaghetti caves.
+    pub spaghetti_width: f32,
+}
+
+impl Default for DensitySettings {
+    fn default() -> Self {
+        Self {
+            block: "default:stone".into(),
+
+            frequency: 0.01,
+            octaves: 4,
+            amplitude: 32.0,
+            base_height: 0.0,
+            height_gradient: 1.0,
+            threshold: 0.0,
+
+            cheese_frequency: 0.02,
+            cheese_threshold: 0.6,
+
+            spaghetti_frequency: 0.015,
+            spaghetti_width: 0.06,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DensityGenerator {
+    pub settings: DensitySettings,
+}
+
+impl DensityGenerator {
+    pub fn new(settings: DensitySettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl ChunkGenerator for DensityGenerator {
+    fn generate(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk {
+        let DensitySettings {
+            ref block,
+            frequency,
+            octaves,
+            amplitude,
+            base_height,
+            height_gradient,
+            threshold,
+            cheese_frequency,
+            cheese_threshold,
+            spaghetti_frequency,
+            spaghetti_width,
+        } = self.settings;
+
+        let block_opt = lookup_block(block_library, block);
+
+        let seed = noise_seed(seed);
+
+        let mut terrain = FastNoiseLite::with_seed(seed.wrapping_add(TERRAIN_SEED));
+        terrain.set_frequency(Some(frequency));
+        terrain.set_fractal_type(Some(FractalType::FBm));
+        terrain.set_fractal_octaves(Some(octaves));
+
+        let mut cheese = FastNoiseLite::with_seed(seed.wrapping_add(CHEESE_SEED));
+        cheese.set_frequency(Some(cheese_frequency));
+
+        let mut spaghetti_a = FastNoiseLite::with_seed(seed.wrapping_add(SPAGHETTI_A_SEED));
+        spaghetti_a.set_frequency(Some(spaghetti_frequency));
+
+        let mut spaghetti_b = FastNoiseLite::with_seed(seed.wrapping_add(SPAGHETTI_B_SEED));
+        spaghetti_b.set_frequency(Some(spaghetti_frequency));
+
+        from_fn(chunk_pos, block_library, |voxel_pos| {
+            let [x, y, z] = voxel_pos.as_vec3().to_array();
+
+            let density =
+                terrain.get_noise_3d(x, y, z) * amplitude - (y - base_height) * height_gradient;
+            if density <= threshold {
+                return None;
+            }
+
+            if cheese.get_noise_3d(x, y, z) > cheese_threshold {
+                return None;
+            }
+
+            if spaghetti_a.get_noise_3d(x, y, z).abs() < spaghetti_width
+                && spaghetti_b.get_noise_3d(x, y, z).abs() < spaghetti_width
+            {
+                return None;
+            }
+
+            block_opt
+        })
+    }
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::chunk::unpad;
+
+use super::{
+    decoration::{Decorator, Feature, Placement},
+    lookup_block,
+};
+
+/// A trunk topped with a sphere of leaves, placed on the surface.
+#[derive(Debug, Clone)]
+pub struct TreeFeature {
+    pub trunk: String,
+    pub leaves: String,
+    /// Block the tree must stand on, any block if `None`.
+    pub soil: Option<String>,
+    pub per_chunk: u32,
+    pub min_height: u32,
+    pub max_height: u32,
+    pub leaf_radius: i32,
+}
+
+impl Feature for TreeFeature {
+    fn decorate(&self, decorator: &mut Decorator) {
+        let trunk = lookup_block(decorator.block_library, &self.trunk);
+        let leaves = lookup_block(decorator.block_library, &self.leaves);
+        let soil = self
+            .soil
+            .as_ref()
+            .map(|soil| lookup_block(decorator.block_library, soil));
+
+        for _ in 0..self.per_chunk {
+            let column = decorator.random_column();
+            let height = decorator
+                .rng
+                .random_range(self.min_height..=self.max_height) as i32;
+
+            let Some(ground) = decorator.surface(column) else {
+                continue;
+            };
+
+            let ground_pos = IVec3::new(column.x, ground, column.y);
+            if let Some(soil) = soil
+                && decorator.get(ground_pos).flatten() != soil
+            {
+                continue;
+            }
+
+            let top = ground_pos + IVec3::Y * height;
+
+            let radius = self.leaf_radius;
+            for z in -radius..=radius {
+                for y in -radius..=radius {
+                    for x in -radius..=radius {
+                        let offset = IVec3::new(x, y, z);
+                        if offset.length_squared() <= radius * radius {
+                            decorator.set(top + offset, leaves, Placement::IfEmpty);
+                        }
+                    }
+                }
+            }
+
+            for y in 1..=height {
+                decorator.set(ground_pos + IVec3::Y * y, trunk, Placement::Replace);
+            }
+        }
+    }
+}
+
+/// Random walk veins replacing `replaces`.
+#[derive(Debug, Clone)]
+pub struct OreFeature {
+    pub ore: String,
+    pub replaces: String,
+    pub veins_per_chunk: u32,
+    pub vein_size: u32,
+    pub min_y: i32,
+    pub max_y: i32,
+}
+
+impl Feature for OreFeature {
+    fn decorate(&self, decorator: &mut Decorator) {
+        let ore = lookup_block(decorator.block_library, &self.ore);
+        let replaces = lookup_block(decorator.block_library, &self.replaces);
+
+        let origin = decorator.chunk_origin();
+
+        for _ in 0..self.veins_per_chunk {
+            let column = decorator.random_column();
+            let y = decorator.rng.random_range(self.min_y..=self.max_y);
+
+            // veins originate in exactly one chunk
+            let local_y = y - origin.y;
+            if !(1..=unpad::LEN as i32).contains(&local_y) {
+                continue;
+            }
+
+            let mut pos = IVec3::new(column.x, y, column.y);
+            for _ in 0..self.vein_size {
+                // the vein may wander into chunks that don't exist yet,
+                // in which case its blocks can't be checked and are skipped
+                if let Some(voxel_opt) = decorator.get(pos)
+                    && voxel_opt == replaces
+                {
+                    decorator.set(pos, ore, Placement::Replace);
+                }
+
+                let axis = decorator.rng.random_range(0..3);
+                let step = if decorator.rng.random_bool(0.5) {
+                    1
+                } else {
+                    -1
+                };
+                pos[axis] += step;
+            }
+        }
+    }
+}
+
+/// A fixed arrangement of blocks placed on the surface with `chance` per chunk.
+///
+/// `chance` is clamped to `0..=1`, NaN never places.
+#[derive(Debug, Clone)]
+pub struct StructureFeature {
+    /// Offsets from the surface voxel above the ground.
+    pub blocks: Vec<(IVec3, String)>,
+    pub chance: f64,
+}
+
+impl Feature for StructureFeature {
+    fn decorate(&self, decorator: &mut Decorator) {
+        // `random_bool` panics outside of `0..=1`
+        let chance = if self.chance.is_nan() {
+            0.0
+        } else {
+            self.chance.clamp(0.0, 1.0)
+        };
+
+        if !decorator.rng.random_bool(chance) {
+            return;
+        }
+
+        let column = decorator.random_column();
+        let Some(ground) = decorator.surface(column) else {
+            return;
+        };
+
+        let base = IVec3::new(column.x, ground + 1, column.y);
+
+        for (offset, block) in &self.blocks {
+            let voxel_opt = lookup_block(decorator.block_library, block);
+            decorator.set(base + *offset, voxel_opt, Placement::Replace);
+        }
+    }
+}
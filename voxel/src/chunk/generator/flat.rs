@@ -0,0 +1,43 @@
+use crate::block_lib::BlockLibrary;
+
+use super::{Chunk, ChunkGenerator, ChunkPos, from_fn, lookup_block};
+
+/// Horizontal layers stacked upward from `base`, empty above and solid below.
+#[derive(Debug, Clone)]
+pub struct FlatGenerator {
+    pub base: i32,
+    /// `(block, thickness)` from the bottom up.
+    pub layers: Vec<(String, u32)>,
+}
+
+impl FlatGenerator {
+    pub fn new(base: i32, block: impl Into<String>) -> Self {
+        Self {
+            base,
+            layers: vec![(block.into(), 1)],
+        }
+    }
+}
+
+impl ChunkGenerator for FlatGenerator {
+    fn generate(&self, _: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk {
+        let mut tops = Vec::with_capacity(self.layers.len());
+        let mut top = self.base;
+        for (block, thickness) in &self.layers {
+            top += *thickness as i32;
+            tops.push((top, lookup_block(block_library, block)));
+        }
+
+        let bottom_opt = tops.first().and_then(|(_, block_opt)| *block_opt);
+
+        from_fn(chunk_pos, block_library, |voxel_pos| {
+            if voxel_pos.y < self.base {
+                return bottom_opt;
+            }
+
+            tops.iter()
+                .find(|(top, _)| voxel_pos.y < *top)
+                .and_then(|(_, block_opt)| *block_opt)
+        })
+    }
+}
@@ -0,0 +1,120 @@
+pub mod biome;
+pub mod decoration;
+pub mod density;
+pub mod feature;
+pub mod flat;
+pub mod noise;
+pub mod stored;
+
+use bevy::prelude::*;
+use std::sync::Arc;
+
+use crate::block_lib::BlockLibrary;
+
+use super::{
+    Chunk, ChunkPos, VoxelIndex, chunk_origin,
+    pad::{LEN, linearize},
+};
+
+pub use biome::BiomeGenerator;
+pub use decoration::{DecoratedGenerator, Feature, PendingWrites};
+pub use density::{DensityGenerator, DensitySettings};
+pub use feature::{OreFeature, StructureFeature, TreeFeature};
+pub use flat::FlatGenerator;
+pub use noise::NoiseGenerator;
+pub use stored::StoredGenerator;
+
+/// Produces the voxels of a chunk, including its padding.
+///
+/// Generators must be deterministic in `seed` and `chunk_pos`
+/// so regenerated chunks match their neighbours.
+pub trait ChunkGenerator: Send + Sync + 'static {
+    fn generate(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk;
+
+    /// Like `generate`, also returning `true` if the chunk was loaded from
+    /// a `ChunkStorage` rather than generated.
+    fn load(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> (Chunk, bool) {
+        (self.generate(seed, chunk_pos, block_library), false)
+    }
+}
+
+impl<F> ChunkGenerator for F
+where
+    F: Fn(u64, ChunkPos, &BlockLibrary) -> Chunk + Send + Sync + 'static,
+{
+    fn generate(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk {
+        self(seed, chunk_pos, block_library)
+    }
+}
+
+/// The generator and seed used to fill a `Terrain`.
+#[derive(Component, Clone)]
+pub struct TerrainGenerator {
+    pub seed: u64,
+    pub generator: Arc<dyn ChunkGenerator>,
+}
+
+impl TerrainGenerator {
+    pub fn new(seed: u64, generator: impl ChunkGenerator) -> Self {
+        Self {
+            seed,
+            generator: Arc::new(generator),
+        }
+    }
+
+    #[inline]
+    pub fn generate(&self, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk {
+        self.generator.generate(self.seed, chunk_pos, block_library)
+    }
+
+    /// See `ChunkGenerator::load`.
+    #[inline]
+    pub fn load(&self, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> (Chunk, bool) {
+        self.generator.load(self.seed, chunk_pos, block_library)
+    }
+}
+
+impl Default for TerrainGenerator {
+    fn default() -> Self {
+        Self::new(0, NoiseGenerator::default())
+    }
+}
+
+/// Builds a chunk by evaluating `f` at every voxel position, padding included.
+pub fn from_fn(
+    chunk_pos: ChunkPos,
+    block_library: &BlockLibrary,
+    mut f: impl FnMut(IVec3) -> Option<VoxelIndex>,
+) -> Chunk {
+    let mut chunk = Chunk::EMPTY;
+
+    let chunk_origin = chunk_origin(chunk_pos);
+
+    for offset_z in 0..LEN as u32 {
+        for offset_y in 0..LEN as u32 {
+            for offset_x in 0..LEN as u32 {
+                let voxel_pos = chunk_origin + UVec3::new(offset_x, offset_y, offset_z).as_ivec3();
+
+                let index = linearize([offset_x, offset_y, offset_z]);
+
+                chunk.voxels.set(index, f(voxel_pos));
+            }
+        }
+    }
+
+    chunk.compact();
+    chunk.build_masks(block_library);
+
+    chunk
+}
+
+/// Resolves `namespace:name`, logging if it is missing.
+pub fn lookup_block(block_library: &BlockLibrary, identifier: &str) -> Option<VoxelIndex> {
+    let voxel_opt = block_library.lookup(identifier);
+
+    if voxel_opt.is_none() {
+        warn_once!("Generator block {identifier} is not in the BlockLibrary");
+    }
+
+    voxel_opt
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use fastnoise_lite::FastNoiseLite;
+
+use crate::block_lib::BlockLibrary;
+
+use super::{Chunk, ChunkGenerator, ChunkPos, from_fn, lookup_block};
+
+/// `seed` folded into the `i32` seed of `FastNoiseLite`, keeping its high half.
+#[inline]
+pub fn noise_seed(seed: u64) -> i32 {
+    (seed ^ (seed >> 32)) as i32
+}
+
+// Offsets added to `noise_seed` for each noise field of the generators,
+// kept apart so no two fields share a pattern. `NoiseGenerator` uses the
+// seed as is.
+
+/// `DensityGenerator` terrain.
+pub const TERRAIN_SEED: i32 = 8;
+/// `DensityGenerator` cheese caves.
+pub const CHEESE_SEED: i32 = 9;
+/// `DensityGenerator` spaghetti caves.
+pub const SPAGHETTI_A_SEED: i32 = 10;
+pub const SPAGHETTI_B_SEED: i32 = 11;
+/// `BiomeGenerator` climate.
+pub const TEMPERATURE_SEED: i32 = 16;
+pub const HUMIDITY_SEED: i32 = 17;
+/// `BiomeGenerator` heights, plus the index of the biome.
+pub const HEIGHT_SEED: i32 = 32;
+
+/// 2D heightmap from a single layer of noise.
+#[derive(Debug, Clone)]
+pub struct NoiseGenerator {
+    pub block: String,
+    pub frequency: f32,
+    pub amplitude: f32,
+    pub base_height: f32,
+}
+
+impl Default for NoiseGenerator {
+    fn default() -> Self {
+        Self {
+            block: "default:stone".into(),
+            frequency: 0.01,
+            amplitude: 32.0,
+            base_height: 0.0,
+        }
+    }
+}
+
+impl ChunkGenerator for NoiseGenerator {
+    fn generate(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk {
+        let block_opt = lookup_block(block_library, &self.block);
+
+        let mut noise = FastNoiseLite::with_seed(noise_seed(seed));
+        noise.set_frequency(Some(self.frequency));
+
+        from_fn(chunk_pos, block_library, |voxel_pos| {
+            let h = noise.get_noise_2d(voxel_pos.x as f32, voxel_pos.z as f32) * self.amplitude
+                + self.base_height;
+
+            if voxel_pos.y as f32 > h {
+                None
+            } else {
+                block_opt
+            }
+        })
+    }
+}
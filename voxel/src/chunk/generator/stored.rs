@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use std::sync::Arc;
+
+use crate::{block_lib::BlockLibrary, save::ChunkStorage};
+
+use super::{Chunk, ChunkGenerator, ChunkPos};
+
+/// Loads saved chunks from a `ChunkStorage`, generating missing ones with `fallback`.
+#[derive(Clone)]
+pub struct StoredGenerator {
+    pub storage: ChunkStorage,
+    pub fallback: Arc<dyn ChunkGenerator>,
+}
+
+impl StoredGenerator {
+    pub fn new(storage: ChunkStorage, fallback: impl ChunkGenerator) -> Self {
+        Self {
+            storage,
+            fallback: Arc::new(fallback),
+        }
+    }
+}
+
+impl ChunkGenerator for StoredGenerator {
+    fn generate(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Chunk {
+        self.load(seed, chunk_pos, block_library).0
+    }
+
+    fn load(&self, seed: u64, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> (Chunk, bool) {
+        match self.storage.load_chunk(chunk_pos, block_library) {
+            Ok(Some(chunk)) => return (chunk, true),
+            Ok(None) => {}
+            Err(e) => error!("Error {e} loading chunk {chunk_pos}, regenerating"),
+        }
+
+        self.fallback.load(seed, chunk_pos, block_library)
+    }
+}
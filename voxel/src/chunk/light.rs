@@ -0,0 +1,501 @@
+use bevy::prelude::*;
+use dashmap::DashMap;
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::block_lib::BlockLibrary;
+
+use super::{
+    ChunkMap, ChunkPos, DirtyChunks, VoxelIndex, chunk_origin,
+    pad::{self, VOL},
+    padded_chunks,
+    padding::{NEIGHBOURS, padding_region, to_neighbour},
+    unpad, voxel_chunk_pos, voxel_local_pos,
+};
+
+// Every voxel stores sky light in its high nibble and block light in
+// its low nibble. Light spreads by BFS, losing `max(1, light_opacity)`
+// per voxel, except full sky light which travels straight down through
+// clear voxels without loss. Removal floods out light that was brighter
+// than its surroundings and then refills the hole from what is left.
+//
+// Light is stored in the padded layout and mirrored into the padding of
+// neighbours like voxels are, so the mesher can sample across borders.
+// Sky light enters the top of a chunk whose upper neighbour isn't loaded.
+
+pub const MAX_LIGHT: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+impl LightChannel {
+    pub const ALL: [Self; 2] = [Self::Sky, Self::Block];
+
+    #[inline]
+    pub const fn get(self, light: u8) -> u8 {
+        match self {
+            Self::Sky => light >> 4,
+            Self::Block => light & 0xF,
+        }
+    }
+
+    #[inline]
+    pub const fn with(self, light: u8, level: u8) -> u8 {
+        match self {
+            Self::Sky => (light & 0xF) | (level << 4),
+            Self::Block => (light & 0xF0) | level,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum ChunkLight {
+    Uniform(u8),
+    Dense(Box<[u8]>),
+}
+
+impl ChunkLight {
+    pub const DARK: Self = Self::Uniform(0);
+
+    #[inline]
+    pub fn get(&self, pos: UVec3) -> u8 {
+        match self {
+            Self::Uniform(light) => *light,
+            Self::Dense(lights) => lights[pad::linearize(pos)],
+        }
+    }
+
+    pub fn set(&mut self, pos: UVec3, light: u8) {
+        match self {
+            Self::Uniform(uniform) => {
+                if *uniform == light {
+                    return;
+                }
+
+                let mut lights = vec![*uniform; VOL].into_boxed_slice();
+                lights[pad::linearize(pos)] = light;
+                *self = Self::Dense(lights);
+            }
+            Self::Dense(lights) => lights[pad::linearize(pos)] = light,
+        }
+    }
+}
+
+/// Light of every chunk in the `ChunkMap` of the same terrain.
+#[derive(Component, Default, Clone, Deref)]
+pub struct LightMap(pub Arc<DashMap<ChunkPos, ChunkLight>>);
+
+impl LightMap {
+    /// `None` if the chunk containing `voxel_pos` isn't lit.
+    pub fn get_light(&self, voxel_pos: IVec3) -> Option<u8> {
+        let chunk_light = self.get(&voxel_chunk_pos(voxel_pos))?;
+        Some(chunk_light.get(voxel_local_pos(voxel_pos)))
+    }
+}
+
+/// Lighting work for `update_light`.
+#[derive(Component, Default)]
+pub struct LightUpdates {
+    /// Chunks that were inserted and need lighting.
+    pub chunks: VecDeque<ChunkPos>,
+    /// Voxels that changed and need relighting.
+    pub voxels: Vec<IVec3>,
+}
+
+struct Lighter<'a> {
+    chunk_map: &'a ChunkMap,
+    light_map: &'a LightMap,
+    block_library: &'a BlockLibrary,
+    dirty_chunks: &'a mut DirtyChunks,
+}
+
+const DIRECTIONS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+impl Lighter<'_> {
+    #[inline]
+    fn voxel(&self, voxel_pos: IVec3) -> Option<Option<VoxelIndex>> {
+        self.chunk_map.get_voxel(voxel_pos)
+    }
+
+    #[inline]
+    fn opacity(&self, voxel_opt: Option<VoxelIndex>) -> u8 {
+        voxel_opt.map_or(0, |voxel| self.block_library[voxel].light_opacity)
+    }
+
+    #[inline]
+    fn emission(&self, voxel_opt: Option<VoxelIndex>) -> u8 {
+        voxel_opt.map_or(0, |voxel| self.block_library[voxel].emission)
+    }
+
+    #[inline]
+    fn level(&self, voxel_pos: IVec3, channel: LightChannel) -> u8 {
+        self.light_map
+            .get_light(voxel_pos)
+            .map_or(0, |light| channel.get(light))
+    }
+
+    /// Writes to every lit chunk containing `voxel_pos`, marking those that changed.
+    fn set_level(&mut self, voxel_pos: IVec3, channel: LightChannel, level: u8) {
+        for (chunk_pos, pos) in padded_chunks(voxel_pos) {
+            let Some(mut chunk_light) = self.light_map.get_mut(&chunk_pos) else {
+                continue;
+            };
+
+            let light = chunk_light.get(pos);
+            let new = channel.with(light, level);
+            if light != new {
+                chunk_light.set(pos, new);
+                self.dirty_chunks.mark(chunk_pos);
+            }
+        }
+    }
+
+    /// Level reaching the voxel at `to` from a neighbour at `level`, `dir` away.
+    #[inline]
+    fn spread(channel: LightChannel, level: u8, dir: IVec3, opacity: u8) -> u8 {
+        if opacity >= MAX_LIGHT {
+            0
+        } else if channel == LightChannel::Sky
+            && dir == IVec3::NEG_Y
+            && level == MAX_LIGHT
+            && opacity == 0
+        {
+            MAX_LIGHT
+        } else {
+            level.saturating_sub(opacity.max(1))
+        }
+    }
+
+    fn propagate(&mut self, channel: LightChannel, mut queue: VecDeque<IVec3>) {
+        while let Some(voxel_pos) = queue.pop_front() {
+            let level = self.level(voxel_pos, channel);
+            if level <= 1 {
+                continue;
+            }
+
+            for dir in DIRECTIONS {
+                let neighbour = voxel_pos + dir;
+                let Some(voxel_opt) = self.voxel(neighbour) else {
+                    continue;
+                };
+
+                let new = Self::spread(channel, level, dir, self.opacity(voxel_opt));
+                if new > self.level(neighbour, channel) {
+                    self.set_level(neighbour, channel, new);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    /// Darkens everything lit by the voxels in `queue`, returning voxels to relight from.
+    fn remove(
+        &mut self,
+        channel: LightChannel,
+        mut queue: VecDeque<(IVec3, u8)>,
+    ) -> VecDeque<IVec3> {
+        let mut relight = VecDeque::new();
+
+        while let Some((voxel_pos, level)) = queue.pop_front() {
+            for dir in DIRECTIONS {
+                let neighbour = voxel_pos + dir;
+                let Some(voxel_opt) = self.voxel(neighbour) else {
+                    continue;
+                };
+
+                let neighbour_level = self.level(neighbour, channel);
+                if neighbour_level == 0 {
+                    continue;
+                }
+
+                let downward_sky = channel == LightChannel::Sky
+                    && dir == IVec3::NEG_Y
+                    && level == MAX_LIGHT
+                    && neighbour_level == MAX_LIGHT;
+
+                if neighbour_level < level || downward_sky {
+                    let emission = match channel {
+                        LightChannel::Sky => 0,
+                        LightChannel::Block => self.emission(voxel_opt),
+                    };
+
+                    self.set_level(neighbour, channel, emission);
+                    queue.push_back((neighbour, neighbour_level));
+
+                    if emission > 0 {
+                        relight.push_back(neighbour);
+                    }
+                } else {
+                    relight.push_back(neighbour);
+                }
+            }
+        }
+
+        relight
+    }
+
+    /// Relights around voxels whose block changed.
+    fn update_voxels(&mut self, voxel_positions: &[IVec3]) {
+        for channel in LightChannel::ALL {
+            let mut removal = VecDeque::new();
+
+            for voxel_pos in voxel_positions {
+                let level = self.level(*voxel_pos, channel);
+                if level > 0 {
+                    self.set_level(*voxel_pos, channel, 0);
+                    removal.push_back((*voxel_pos, level));
+                }
+            }
+
+            let mut relight = self.remove(channel, removal);
+
+            for voxel_pos in voxel_positions {
+                let Some(voxel_opt) = self.voxel(*voxel_pos) else {
+                    continue;
+                };
+
+                if channel == LightChannel::Block {
+                    let emission = self.emission(voxel_opt);
+                    if emission > 0 {
+                        self.set_level(*voxel_pos, channel, emission);
+                        relight.push_back(*voxel_pos);
+                    }
+                }
+
+                relight.extend(DIRECTIONS.map(|dir| *voxel_pos + dir));
+            }
+
+            self.propagate(channel, relight);
+        }
+    }
+
+    /// Lights a chunk that was just inserted and updates its neighbours.
+    fn light_chunk(&mut self, chunk_pos: ChunkPos) {
+        let Some(chunk) = self.chunk_map.get(&chunk_pos).map(|chunk| chunk.clone()) else {
+            return;
+        };
+
+        let origin = chunk_origin(chunk_pos);
+        let above_loaded = self.light_map.contains_key(&(chunk_pos + IVec3::Y));
+
+        // a clear chunk under open sky is fully lit without walking its columns
+        let open_sky = !above_loaded
+            && chunk.voxels().uniform().is_some_and(|voxel_opt| {
+                self.opacity(voxel_opt) == 0 && self.emission(voxel_opt) == 0
+            });
+
+        let initial = if open_sky {
+            ChunkLight::Uniform(LightChannel::Sky.with(0, MAX_LIGHT))
+        } else {
+            ChunkLight::DARK
+        };
+
+        self.light_map.insert(chunk_pos, initial);
+        self.fill_padding(chunk_pos);
+
+        if !above_loaded && let Some(mut chunk_light) = self.light_map.get_mut(&chunk_pos) {
+            for pos in padding_region(IVec3::Y) {
+                let light = chunk_light.get(pos);
+                chunk_light.set(pos, LightChannel::Sky.with(light, MAX_LIGHT));
+            }
+        }
+
+        self.share_padding(chunk_pos);
+
+        let mut sky = VecDeque::new();
+        let mut block = VecDeque::new();
+
+        if !open_sky {
+            // sky light falling through each column
+            for z in 1..=unpad::LEN as u32 {
+                for x in 1..=unpad::LEN as u32 {
+                    let top = UVec3::new(x, pad::LEN as u32 - 1, z);
+                    let mut level = if above_loaded {
+                        self.level(origin + top.as_ivec3(), LightChannel::Sky)
+                    } else {
+                        MAX_LIGHT
+                    };
+
+                    for y in (1..=unpad::LEN as u32).rev() {
+                        let pos = UVec3::new(x, y, z);
+                        let opacity = self.opacity(chunk.get(pos));
+                        level = Self::spread(LightChannel::Sky, level, IVec3::NEG_Y, opacity);
+                        if level == 0 {
+                            break;
+                        }
+
+                        let voxel_pos = origin + pos.as_ivec3();
+                        self.set_level(voxel_pos, LightChannel::Sky, level);
+                        sky.push_back(voxel_pos);
+
+                        if level < MAX_LIGHT {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // emissive blocks
+            let emissive = chunk
+                .voxels()
+                .uniform()
+                .is_none_or(|voxel_opt| self.emission(voxel_opt) > 0);
+
+            if emissive {
+                for z in 1..=unpad::LEN as u32 {
+                    for y in 1..=unpad::LEN as u32 {
+                        for x in 1..=unpad::LEN as u32 {
+                            let pos = UVec3::new(x, y, z);
+                            let emission = self.emission(chunk.get(pos));
+                            if emission > 0 {
+                                let voxel_pos = origin + pos.as_ivec3();
+                                self.set_level(voxel_pos, LightChannel::Block, emission);
+                                block.push_back(voxel_pos);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // light flows both ways across faces shared with lit neighbours
+        for offset in NEIGHBOURS {
+            if offset.abs().element_sum() != 1
+                || !self.light_map.contains_key(&(chunk_pos + offset))
+            {
+                continue;
+            }
+
+            for pos in padding_region(offset) {
+                let outside = origin + pos.as_ivec3();
+                let inside = outside - offset;
+
+                sky.extend([outside, inside]);
+                block.extend([outside, inside]);
+            }
+        }
+
+        // the chunk below may have assumed open sky where this chunk now is
+        if self.light_map.contains_key(&(chunk_pos - IVec3::Y)) {
+            let mut removal = VecDeque::new();
+
+            for z in 1..=unpad::LEN as i32 {
+                for x in 1..=unpad::LEN as i32 {
+                    let above = origin + IVec3::new(x, 1, z);
+                    let below = above - IVec3::Y;
+
+                    if self.level(below, LightChannel::Sky) == MAX_LIGHT
+                        && self.level(above, LightChannel::Sky) < MAX_LIGHT
+                    {
+                        self.set_level(below, LightChannel::Sky, 0);
+                        removal.push_back((below, MAX_LIGHT));
+                    }
+                }
+            }
+
+            sky.extend(self.remove(LightChannel::Sky, removal));
+        }
+
+        self.propagate(LightChannel::Sky, sky);
+        self.propagate(LightChannel::Block, block);
+
+        self.dirty_chunks.mark(chunk_pos);
+    }
+
+    /// Copies the border light of `chunk_pos` into the padding of every lit neighbour.
+    fn share_padding(&mut self, chunk_pos: ChunkPos) {
+        for offset in NEIGHBOURS {
+            let neighbour_pos = chunk_pos + offset;
+            if !self.light_map.contains_key(&neighbour_pos) {
+                continue;
+            }
+
+            let writes = {
+                let Some(chunk_light) = self.light_map.get(&chunk_pos) else {
+                    return;
+                };
+
+                padding_region(-offset)
+                    .map(|pos| (pos, chunk_light.get(to_neighbour(pos, -offset))))
+                    .collect::<Vec<_>>()
+            };
+
+            let Some(mut neighbour) = self.light_map.get_mut(&neighbour_pos) else {
+                continue;
+            };
+
+            let mut changed = false;
+            for (pos, light) in writes {
+                if neighbour.get(pos) != light {
+                    neighbour.set(pos, light);
+                    changed = true;
+                }
+            }
+
+            if changed {
+                self.dirty_chunks.mark(neighbour_pos);
+            }
+        }
+    }
+
+    /// Copies the border light of every lit neighbour into the padding of `chunk_pos`.
+    fn fill_padding(&mut self, chunk_pos: ChunkPos) {
+        let mut writes = Vec::new();
+
+        for offset in NEIGHBOURS {
+            let Some(neighbour) = self.light_map.get(&(chunk_pos + offset)) else {
+                continue;
+            };
+
+            writes.extend(
+                padding_region(offset).map(|pos| (pos, neighbour.get(to_neighbour(pos, offset)))),
+            );
+        }
+
+        if let Some(mut chunk_light) = self.light_map.get_mut(&chunk_pos) {
+            for (pos, light) in writes {
+                chunk_light.set(pos, light);
+            }
+        }
+    }
+}
+
+/// Lights inserted chunks, up to `budget` per terrain per frame, then relights edited voxels.
+pub fn update_light(
+    block_library: Res<BlockLibrary>,
+    terrains: Query<(&ChunkMap, &LightMap, &mut LightUpdates, &mut DirtyChunks)>,
+) {
+    // lighting a chunk floods through its neighbours, keep it bounded
+    const BUDGET: usize = 8;
+
+    for (chunk_map, light_map, mut light_updates, mut dirty_chunks) in terrains {
+        let mut lighter = Lighter {
+            chunk_map,
+            light_map,
+            block_library: &block_library,
+            dirty_chunks: &mut dirty_chunks,
+        };
+
+        for _ in 0..BUDGET {
+            let Some(chunk_pos) = light_updates.chunks.pop_front() else {
+                break;
+            };
+
+            lighter.light_chunk(chunk_pos);
+        }
+
+        if !light_updates.voxels.is_empty() {
+            let voxels = std::mem::take(&mut light_updates.voxels);
+            lighter.update_voxels(&voxels);
+        }
+    }
+}
@@ -0,0 +1,507 @@
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    render::renderer::{RenderDevice, RenderQueue},
+    tasks::{AsyncComputeTaskPool, Task, block_on, poll_once},
+};
+use std::ops::Range;
+
+use crate::{block_lib::BlockLibrary, render::alloc_buffer::AllocBuffer, viewer::Viewer};
+
+use super::{
+    Chunk, ChunkMap, ChunkMesh, ChunkPos, DirtyChunks, MeshingSettings, ModelQuad, VoxelIndex,
+    VoxelQuad, Voxels, chunk_origin, pad, padding::NEIGHBOURS, point_chunk_pos, task::mesh_chunk,
+    unpad, voxel_chunk_pos,
+};
+
+// A LOD region of level `L` covers `2^L` chunks along each axis and is
+// downsampled into one chunk where every voxel stands for a `2^L` cube of
+// voxels, holding the most common non-empty block if at least half of the
+// cube is filled. It is meshed by the same `Mesher` and `ModelMesher`
+// with their quads scaled by `2^L`. Chunks inside a selected region
+// aren't drawn.
+//
+// Padding is only downsampled toward neighbouring regions of the same
+// level. Toward anything finer or coarser it is left empty so the border
+// faces are kept and cover the seam between the two levels.
+
+pub const MAX_LOD: u8 = 3;
+
+const FACES: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LodPos {
+    /// `1..=MAX_LOD`
+    pub level: u8,
+    pub pos: ChunkPos,
+}
+
+impl LodPos {
+    #[inline]
+    pub fn containing(chunk_pos: ChunkPos, level: u8) -> Self {
+        Self {
+            level,
+            pos: chunk_pos >> level as i32,
+        }
+    }
+
+    /// Chunks along each axis.
+    #[inline]
+    pub fn scale(self) -> i32 {
+        1 << self.level
+    }
+
+    /// First and last chunk of the region.
+    #[inline]
+    pub fn chunks(self) -> (ChunkPos, ChunkPos) {
+        let min = self.pos * self.scale();
+        (min, min + IVec3::splat(self.scale() - 1))
+    }
+
+    /// Chebyshev distance in chunks from `chunk_pos` to the nearest chunk of the region.
+    #[inline]
+    pub fn distance(self, chunk_pos: ChunkPos) -> i32 {
+        let (min, max) = self.chunks();
+        (min - chunk_pos)
+            .max(chunk_pos - max)
+            .max(IVec3::ZERO)
+            .max_element()
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LodSettings {
+    /// Distance in chunks from the nearest viewer at which each level
+    /// starts, finest first.
+    pub distances: [i32; MAX_LOD as usize],
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            distances: [8, 16, 32],
+        }
+    }
+}
+
+/// Selected LOD regions of a terrain and their meshes.
+#[derive(Component, Default)]
+pub struct ChunkLods {
+    origins: Vec<ChunkPos>,
+    loaded: usize,
+    selected: HashSet<LodPos>,
+    dirty: HashSet<LodPos>,
+    tasks: HashMap<LodPos, Task<ChunkMesh>>,
+    meshes: HashMap<LodPos, ChunkMesh>,
+}
+
+impl ChunkLods {
+    /// Whether `chunk_pos` is drawn by a LOD region instead of its own mesh.
+    pub fn is_covered(&self, chunk_pos: ChunkPos) -> bool {
+        (1..=MAX_LOD).any(|level| {
+            self.selected
+                .contains(&LodPos::containing(chunk_pos, level))
+        })
+    }
+
+    pub fn meshes(&self) -> impl Iterator<Item = (&LodPos, &ChunkMesh)> {
+        self.meshes.iter()
+    }
+
+    /// Selects the coarsest level far enough from every origin for each loaded chunk.
+    fn select(&self, chunk_map: &ChunkMap, settings: &LodSettings) -> HashSet<LodPos> {
+        let mut selected = HashSet::new();
+
+        if self.origins.is_empty() {
+            return selected;
+        }
+
+        for entry in chunk_map.iter() {
+            let chunk_pos = *entry.key();
+
+            for level in (1..=MAX_LOD).rev() {
+                let lod_pos = LodPos::containing(chunk_pos, level);
+                if selected.contains(&lod_pos) {
+                    break;
+                }
+
+                let distance = self
+                    .origins
+                    .iter()
+                    .map(|origin| lod_pos.distance(*origin))
+                    .min()
+                    .unwrap_or(i32::MAX);
+
+                if distance >= settings.distances[level as usize - 1] {
+                    selected.insert(lod_pos);
+                    break;
+                }
+            }
+        }
+
+        selected
+    }
+
+    /// Replaces the selection, dropping regions that left it and marking
+    /// those that joined it, and their neighbours, for meshing.
+    fn reselect(
+        &mut self,
+        selected: HashSet<LodPos>,
+        buffers: Option<(&AllocBuffer<VoxelQuad>, &AllocBuffer<ModelQuad>)>,
+    ) {
+        let changed = self
+            .selected
+            .symmetric_difference(&selected)
+            .copied()
+            .collect::<Vec<_>>();
+
+        for lod_pos in changed {
+            // neighbours of the same level link their padding to it
+            for offset in FACES {
+                self.dirty.insert(LodPos {
+                    level: lod_pos.level,
+                    pos: lod_pos.pos + offset,
+                });
+            }
+
+            if selected.contains(&lod_pos) {
+                self.dirty.insert(lod_pos);
+                continue;
+            }
+
+            // finished tasks of deselected regions are freed in `poll`
+            if let Some(chunk_mesh) = self.meshes.remove(&lod_pos)
+                && let Some((alloc_buffer, model_buffer)) = buffers
+            {
+                chunk_mesh.free(alloc_buffer, model_buffer);
+            }
+        }
+
+        self.selected = selected;
+        self.dirty.retain(|lod_pos| self.selected.contains(lod_pos));
+    }
+
+    /// Returns `false` if `lod_pos` is already being meshed.
+    fn spawn_task(
+        &mut self,
+        chunk_map: ChunkMap,
+        lod_pos: LodPos,
+
+        alloc_buffer: AllocBuffer<VoxelQuad>,
+        model_buffer: AllocBuffer<ModelQuad>,
+
+        block_library: BlockLibrary,
+        settings: MeshingSettings,
+
+        queue: RenderQueue,
+        device: RenderDevice,
+    ) -> bool {
+        if self.tasks.contains_key(&lod_pos) {
+            return false;
+        }
+
+        let linked = FACES
+            .into_iter()
+            .filter(|offset| {
+                self.selected.contains(&LodPos {
+                    level: lod_pos.level,
+                    pos: lod_pos.pos + offset,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let pool = AsyncComputeTaskPool::get();
+
+        let task = pool.spawn(async move {
+            let chunk =
+                chunk_map.downsample(lod_pos, &block_library, |offset| linked.contains(&offset));
+
+            mesh_chunk(
+                &chunk,
+                lod_pos.pos,
+                lod_pos.level,
+                None,
+                &alloc_buffer,
+                &model_buffer,
+                &block_library,
+                settings,
+                &queue,
+                &device,
+            )
+        });
+
+        self.tasks.insert(lod_pos, task);
+
+        true
+    }
+
+    fn poll(
+        &mut self,
+        alloc_buffer: &AllocBuffer<VoxelQuad>,
+        model_buffer: &AllocBuffer<ModelQuad>,
+    ) {
+        let mut finished = Vec::new();
+
+        self.tasks.retain(|lod_pos, task| {
+            if let Some(chunk_mesh) = block_on(poll_once(task)) {
+                finished.push((*lod_pos, chunk_mesh));
+                false
+            } else {
+                true
+            }
+        });
+
+        for (lod_pos, chunk_mesh) in finished {
+            // deselected while meshing
+            if !self.selected.contains(&lod_pos) {
+                chunk_mesh.free(alloc_buffer, model_buffer);
+                continue;
+            }
+
+            if let Some(old) = self.meshes.insert(lod_pos, chunk_mesh) {
+                old.free(alloc_buffer, model_buffer);
+            }
+        }
+    }
+}
+
+/// Blocks of the voxels a downsampled voxel stands for.
+#[derive(Default)]
+struct Cell {
+    total: u32,
+    counts: Vec<(VoxelIndex, u32)>,
+}
+
+impl Cell {
+    #[inline]
+    fn add(&mut self, voxel_opt: Option<VoxelIndex>, count: u32) {
+        self.total += count;
+
+        let Some(voxel) = voxel_opt else {
+            return;
+        };
+
+        match self
+            .counts
+            .iter_mut()
+            .find(|(counted, _)| *counted == voxel)
+        {
+            Some((_, counted)) => *counted += count,
+            None => self.counts.push((voxel, count)),
+        }
+    }
+
+    /// The most common block if at least half of the voxels are filled.
+    fn majority(&self) -> Option<VoxelIndex> {
+        let filled: u32 = self.counts.iter().map(|(_, count)| count).sum();
+        if filled == 0 || filled * 2 < self.total {
+            return None;
+        }
+
+        // ties go to the lower index so the result doesn't depend on iteration order
+        self.counts
+            .iter()
+            .max_by_key(|(voxel, count)| (*count, std::cmp::Reverse(*voxel)))
+            .map(|(voxel, _)| *voxel)
+    }
+}
+
+impl ChunkMap {
+    /// Downsamples the chunks of `lod_pos` into one chunk.
+    ///
+    /// Padding toward a face neighbour is only filled if `linked` returns
+    /// `true` for its offset. Unloaded voxels don't count toward either
+    /// filled or empty.
+    ///
+    /// Every source chunk is walked once under a single lock, uniform ones
+    /// without visiting their voxels.
+    pub fn downsample(
+        &self,
+        lod_pos: LodPos,
+        block_library: &BlockLibrary,
+        linked: impl Fn(IVec3) -> bool,
+    ) -> Chunk {
+        let level = lod_pos.level as i32;
+        let scale = lod_pos.scale();
+        let origin = chunk_origin(lod_pos.pos);
+        let last = pad::LEN as i32 - 1;
+
+        // whether the padding toward `-axis` and `+axis` is filled
+        let faces: [[bool; 2]; 3] = std::array::from_fn(|axis| {
+            let mut offset = IVec3::ZERO;
+            offset[axis] = 1;
+            [linked(-offset), linked(offset)]
+        });
+
+        let mut cells: Vec<Cell> = std::iter::repeat_with(Cell::default)
+            .take(pad::VOL)
+            .collect();
+
+        // downsampled voxel `p` stands for voxels `(p - 1) * scale + 1..=p * scale`
+        let min_chunk = voxel_chunk_pos((origin - IVec3::ONE) * scale + IVec3::ONE);
+        let max_chunk = voxel_chunk_pos((origin + IVec3::splat(last)) * scale);
+
+        for z in min_chunk.z..=max_chunk.z {
+            for y in min_chunk.y..=max_chunk.y {
+                for x in min_chunk.x..=max_chunk.x {
+                    let chunk_pos = IVec3::new(x, y, z);
+                    let Some(chunk) = self.get(&chunk_pos) else {
+                        continue;
+                    };
+
+                    let source_origin = chunk_origin(chunk_pos);
+
+                    // runs of local coordinates falling into the same padded coordinate
+                    let spans: [Vec<(u32, Range<u32>)>; 3] = std::array::from_fn(|axis| {
+                        let mut spans: Vec<(u32, Range<u32>)> = Vec::new();
+
+                        for local in 1..=unpad::LEN as u32 {
+                            let voxel = source_origin[axis] + local as i32;
+                            let p = ((voxel + scale - 1) >> level) - origin[axis];
+
+                            let filled = match p {
+                                0 => faces[axis][0],
+                                p if p == last => faces[axis][1],
+                                p => (1..last).contains(&p),
+                            };
+                            if !filled {
+                                continue;
+                            }
+
+                            match spans.last_mut() {
+                                Some((q, range)) if *q == p as u32 => range.end = local + 1,
+                                _ => spans.push((p as u32, local..local + 1)),
+                            }
+                        }
+
+                        spans
+                    });
+
+                    let uniform = chunk.voxels().uniform();
+
+                    for (pz, zs) in &spans[2] {
+                        for (py, ys) in &spans[1] {
+                            for (px, xs) in &spans[0] {
+                                let cell = &mut cells[pad::linearize([*px, *py, *pz])];
+
+                                if let Some(voxel_opt) = uniform {
+                                    cell.add(
+                                        voxel_opt,
+                                        xs.len() as u32 * ys.len() as u32 * zs.len() as u32,
+                                    );
+                                    continue;
+                                }
+
+                                for lz in zs.clone() {
+                                    for ly in ys.clone() {
+                                        for lx in xs.clone() {
+                                            cell.add(chunk.get(UVec3::new(lx, ly, lz)), 1);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut voxels = Voxels::default();
+        for (index, cell) in cells.iter().enumerate() {
+            if let Some(voxel) = cell.majority() {
+                voxels.set(index, Some(voxel));
+            }
+        }
+
+        voxels.compact();
+        Chunk::from_voxels(voxels, block_library)
+    }
+}
+
+/// Selects LOD regions around every `Viewer` and meshes those that changed.
+///
+/// Must run before `remesh_dirty_chunks` clears the `DirtyChunks`.
+pub fn update_lods(
+    settings: Res<LodSettings>,
+    meshing_settings: Res<MeshingSettings>,
+    block_library: Res<BlockLibrary>,
+    render: Option<(
+        Res<AllocBuffer<VoxelQuad>>,
+        Res<AllocBuffer<ModelQuad>>,
+        Res<RenderQueue>,
+        Res<RenderDevice>,
+    )>,
+    viewers: Query<&GlobalTransform, With<Viewer>>,
+    terrains: Query<(&GlobalTransform, &ChunkMap, &DirtyChunks, &mut ChunkLods)>,
+) {
+    for (terrain_transform, chunk_map, dirty_chunks, mut lods) in terrains {
+        let lods = &mut *lods;
+        let buffers = render
+            .as_ref()
+            .map(|(alloc_buffer, model_buffer, ..)| (&**alloc_buffer, &**model_buffer));
+
+        if let Some((alloc_buffer, model_buffer)) = buffers {
+            lods.poll(alloc_buffer, model_buffer);
+        }
+
+        let world_to_terrain = terrain_transform.affine().inverse();
+
+        let origins = viewers
+            .iter()
+            .map(|transform| {
+                point_chunk_pos(world_to_terrain.transform_point3(transform.translation()))
+            })
+            .collect::<Vec<_>>();
+
+        if origins != lods.origins || chunk_map.len() != lods.loaded || settings.is_changed() {
+            lods.origins = origins;
+            lods.loaded = chunk_map.len();
+
+            let selected = lods.select(chunk_map, &settings);
+            lods.reselect(selected, buffers);
+        }
+
+        if meshing_settings.is_changed() {
+            lods.dirty.extend(lods.selected.iter().copied());
+        }
+
+        // a changed chunk can reach into the padding of the regions around it
+        for chunk_pos in dirty_chunks.iter() {
+            for offset in std::iter::once(IVec3::ZERO).chain(NEIGHBOURS) {
+                for level in 1..=MAX_LOD {
+                    let lod_pos = LodPos::containing(chunk_pos + offset, level);
+                    if lods.selected.contains(&lod_pos) {
+                        lods.dirty.insert(lod_pos);
+                    }
+                }
+            }
+        }
+
+        let Some((alloc_buffer, model_buffer, queue, device)) = &render else {
+            lods.dirty.clear();
+            continue;
+        };
+
+        let mut dirty = std::mem::take(&mut lods.dirty);
+        dirty.retain(|lod_pos| {
+            !lods.spawn_task(
+                chunk_map.clone(),
+                *lod_pos,
+                AllocBuffer::clone(alloc_buffer),
+                AllocBuffer::clone(model_buffer),
+                BlockLibrary::clone(&block_library),
+                *meshing_settings,
+                RenderQueue::clone(queue),
+                RenderDevice::clone(device),
+            )
+        });
+        lods.dirty = dirty;
+    }
+}
@@ -0,0 +1,65 @@
+use std::ops::Index;
+
+use super::pad::AREA;
+
+// A column per `area_yz` with a bit per `x`. Uniform chunks, most of the
+// sky and the deep underground, keep a single column for every `area_yz`
+// instead of `AREA` of them.
+
+#[derive(Clone)]
+pub enum Mask {
+    /// The same column everywhere.
+    Uniform(u64),
+    Dense(Box<[u64; AREA]>),
+}
+
+impl Mask {
+    pub const EMPTY: Self = Self::Uniform(0);
+
+    #[inline]
+    pub const fn filled(bit: bool) -> Self {
+        Self::Uniform(if bit { u64::MAX } else { 0 })
+    }
+
+    /// Columns to write to, allocated on the first write.
+    pub fn dense_mut(&mut self) -> &mut [u64; AREA] {
+        if let Self::Uniform(column) = *self {
+            *self = Self::Dense(Box::new([column; AREA]));
+        }
+
+        match self {
+            Self::Dense(columns) => columns,
+            Self::Uniform(_) => unreachable!(),
+        }
+    }
+
+    /// Frees the columns when they are all the same.
+    pub fn compact(&mut self) {
+        if let Self::Dense(columns) = self
+            && columns.iter().all(|column| *column == columns[0])
+        {
+            let column = columns[0];
+            *self = Self::Uniform(column);
+        }
+    }
+
+    /// Number of bytes allocated for the columns, excluding `self`.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Self::Uniform(_) => 0,
+            Self::Dense(columns) => size_of_val(&**columns),
+        }
+    }
+}
+
+impl Index<usize> for Mask {
+    type Output = u64;
+
+    #[inline]
+    fn index(&self, area_yz: usize) -> &u64 {
+        match self {
+            Self::Uniform(column) => column,
+            Self::Dense(columns) => &columns[area_yz],
+        }
+    }
+}
@@ -1,13 +1,19 @@
-use bevy::math::{IVec3, UVec3};
+use bevy::{
+    math::{IVec3, UVec3},
+    platform::time::Instant,
+};
 use bytemuck::{Pod, Zeroable};
 use enum_map::enum_map;
-use std::ops::Range;
+use std::{ops::Range, time::Duration};
 
-use crate::{block_lib::BlockLibrary, math::signed_axis::*, voxel::Voxel};
+use crate::{
+    block_lib::BlockLibrary,
+    math::{axis::Axis, signed_axis::*},
+};
 
 use super::{
-    Chunk, chunk_origin,
-    pad::{AREA, LEN, VOL},
+    Chunk, ChunkLight, LightChannel, MAX_LIGHT, Mask, VoxelIndex, Voxels, chunk_origin,
+    pad::{AREA, LEN},
     pad::{SHIFT_0, SHIFT_1, SHIFT_2, STRIDE_0, STRIDE_1, STRIDE_2},
 };
 
@@ -15,15 +21,33 @@ use super::{
 // area_*: Y - `SHIFT_0`, Z - `SHIFT_1`,
 // * is replaced by whichever axes are present
 
-// `transparent_mask` and `opaque_mask` must point at `Some(voxel)`.
+// `transparent_mask`, `opaque_mask` and `model_mask` must point at
+// `Some(voxel)`, each voxel in at most one of them.
 // `build_masks` must be called on init, `update_masks` must be
 // called when `voxels` changes.
 
-const UNPADDED_MASK: u64 = !(1 << 63 | 1);
+// Opaque and transparent faces are culled into separate masks and merged
+// into separate ranges so transparent quads can be drawn in their own
+// blended pass. A transparent face against the same block is only hidden
+// if that block `connects_to_same` (glass, not leaves).
+
+// Faces only merge with neighbours of equal AO and light, so lighting
+// can be taken from the quad's first voxel.
+
+pub(super) const UNPADDED_MASK: u64 = !(1 << 63 | 1);
 
 pub struct Mesher {
+    /// Darkens the corners of faces next to opaque voxels. Disabling
+    /// it skips the lookups and lets more faces merge.
+    pub ambient_occlusion: bool,
+    /// Quads are scaled by `2^lod`, see `lod.rs`.
+    pub lod: u8,
+    /// Filled by every `mesh` when `Some`, costs a few timer reads and a
+    /// pass over the visible masks.
+    pub stats: Option<MesherStats>,
     quads: Vec<VoxelQuad>,
     visible_masks: Box<SignedAxisMap<[u64; AREA]>>,
+    transparent_visible_masks: Box<SignedAxisMap<[u64; AREA]>>,
     upward_merged: Box<[u8; LEN]>,
     forward_merged: Box<[u8; AREA]>,
 }
@@ -31,8 +55,12 @@ pub struct Mesher {
 impl Mesher {
     pub fn new() -> Self {
         Self {
+            ambient_occlusion: true,
+            lod: 0,
+            stats: None,
             quads: Vec::new(),
             visible_masks: Box::new(enum_map! { _ => [0; AREA] }),
+            transparent_visible_masks: Box::new(enum_map! { _ => [0; AREA] }),
             upward_merged: Box::new([0; LEN]),
             forward_merged: Box::new([0; AREA]),
         }
@@ -41,18 +69,23 @@ impl Mesher {
     pub fn clear(&mut self) {
         self.quads.clear();
         self.visible_masks.as_mut_array().fill([0; AREA]);
+        self.transparent_visible_masks
+            .as_mut_array()
+            .fill([0; AREA]);
         self.upward_merged.fill(0);
         self.forward_merged.fill(0);
     }
 
     fn face_culling(
         &mut self,
-        voxels: &[Option<Voxel>; VOL],
-        opaque_mask: &[u64; AREA],
-        transparent_mask: &[u64; AREA],
+        voxels: &Voxels,
+        opaque_mask: &Mask,
+        transparent_mask: &Mask,
+        block_library: &BlockLibrary,
     ) {
         for signed_axis in SignedAxis::ALL {
             let visible_mask = &mut self.visible_masks[signed_axis];
+            let transparent_visible_mask = &mut self.transparent_visible_masks[signed_axis];
 
             let vol_adj_offset = match signed_axis {
                 PosX => STRIDE_0 as isize,
@@ -103,28 +136,50 @@ impl Mesher {
                         let vol_x = x << SHIFT_0;
                         let vol_xyz = vol_x | vol_yz;
 
-                        let voxel_opt = voxels[vol_xyz];
+                        let voxel_opt = voxels.get(vol_xyz);
 
                         let adj_index = (vol_xyz as isize + vol_adj_offset) as usize;
-                        let adj_voxel_opt = voxels[adj_index];
+                        let adj_voxel_opt = voxels.get(adj_index);
 
-                        visible_mask[area_yz] |= ((voxel_opt != adj_voxel_opt) as u64) << x;
+                        let visible = voxel_opt != adj_voxel_opt
+                            || !block_library[voxel_opt.unwrap()].connects_to_same;
+
+                        transparent_visible_mask[area_yz] |= (visible as u64) << x;
                     }
                 }
             }
         }
     }
 
+    /// Merges the opaque or transparent visible masks, writing the end of
+    /// each range to `offsets` starting after `offsets[first]`.
     fn face_merging(
         &mut self,
-        voxels: &[Option<Voxel>; VOL],
+        voxels: &Voxels,
         chunk_origin: IVec3,
         block_library: &BlockLibrary,
-    ) -> VoxelQuadOffsets {
-        let mut offsets = [0; 7];
+        opaque_mask: &[u64; AREA],
+        light: Option<&ChunkLight>,
+        transparent: bool,
+        offsets: &mut [u32; 13],
+    ) {
+        let first = if transparent { 6 } else { 0 };
+        let ambient_occlusion = self.ambient_occlusion;
+        let lod = self.lod;
+        let lighting_at = |signed_axis, x, y, z| {
+            face_lighting(ambient_occlusion, opaque_mask, light, signed_axis, x, y, z)
+                | (lod as u32) << 16
+        };
+        // voxel `v` of a LOD chunk covers `(v - 1) * scale + 1..=v * scale`
+        let quad_pos =
+            |local: IVec3| (chunk_origin + local - IVec3::ONE) * (1i32 << lod) + IVec3::ONE;
 
         for (index, signed_axis) in [PosX, PosY, PosZ, NegX, NegY, NegZ].into_iter().enumerate() {
-            let visible_mask = &self.visible_masks[signed_axis];
+            let visible_mask = if transparent {
+                &self.transparent_visible_masks[signed_axis]
+            } else {
+                &self.visible_masks[signed_axis]
+            };
 
             for z in 1..LEN - 1 {
                 let vol_z = z << SHIFT_2;
@@ -158,12 +213,14 @@ impl Mesher {
 
                                 let vol_xy = vol_x | vol_y;
 
-                                let voxel_opt = voxels[vol_xyz];
+                                let voxel_opt = voxels.get(vol_xyz);
                                 let voxel = voxel_opt.unwrap();
+                                let lighting = lighting_at(signed_axis, x, y, z);
 
                                 if self.upward_merged[vol_x] == 0
                                     && (forward_column >> x) & 1 != 0
-                                    && voxel_opt == voxels[vol_xyz + STRIDE_2]
+                                    && voxel_opt == voxels.get(vol_xyz + STRIDE_2)
+                                    && lighting == lighting_at(signed_axis, x, y, z + 1)
                                 {
                                     self.forward_merged[vol_xy] += 1;
                                     continue;
@@ -172,7 +229,8 @@ impl Mesher {
                                 if (upward_column >> x) & 1 != 0
                                     && self.forward_merged[vol_xy]
                                         == self.forward_merged[vol_xy + STRIDE_1]
-                                    && voxel_opt == voxels[vol_xyz + STRIDE_1]
+                                    && voxel_opt == voxels.get(vol_xyz + STRIDE_1)
+                                    && lighting == lighting_at(signed_axis, x, y + 1, z)
                                 {
                                     self.forward_merged[vol_xy] = 0;
                                     self.upward_merged[vol_x] += 1;
@@ -189,10 +247,11 @@ impl Mesher {
                                 self.forward_merged[vol_xy] = 0;
                                 self.upward_merged[vol_x] = 0;
 
-                                let pos = chunk_origin + IVec3::new(x, y, z);
+                                let pos = quad_pos(IVec3::new(x, y, z));
                                 let texture_index = block_library[voxel].textures[signed_axis];
 
-                                let quad = VoxelQuad::new(pos, texture_index, w, h, signed_axis);
+                                let quad =
+                                    VoxelQuad::new(pos, texture_index, w, h, signed_axis, lighting);
                                 self.quads.push(quad);
                             }
                         }
@@ -207,11 +266,13 @@ impl Mesher {
 
                                 let vol_xy = vol_x | vol_y;
 
-                                let voxel_opt = voxels[vol_xyz];
+                                let voxel_opt = voxels.get(vol_xyz);
                                 let voxel = voxel_opt.unwrap();
+                                let lighting = lighting_at(signed_axis, x, y, z);
 
                                 if (forward_column >> x) & 1 != 0
-                                    && voxel_opt == voxels[vol_xyz + STRIDE_2]
+                                    && voxel_opt == voxels.get(vol_xyz + STRIDE_2)
+                                    && lighting == lighting_at(signed_axis, x, y, z + 1)
                                 {
                                     self.forward_merged[vol_xy] += 1;
                                     column &= column - 1;
@@ -226,7 +287,8 @@ impl Mesher {
                                     if (column >> right) & 1 == 0
                                         || self.forward_merged[vol_xy]
                                             != self.forward_merged[r_vol_xy]
-                                        || voxel_opt != voxels[r_vol_xy | vol_z]
+                                        || voxel_opt != voxels.get(r_vol_xy | vol_z)
+                                        || lighting != lighting_at(signed_axis, right, y, z)
                                     {
                                         break;
                                     }
@@ -245,10 +307,11 @@ impl Mesher {
 
                                 self.forward_merged[vol_xy] = 0;
 
-                                let pos = chunk_origin + IVec3::new(x, y, z);
+                                let pos = quad_pos(IVec3::new(x, y, z));
                                 let texture_index = block_library[voxel].textures[signed_axis];
 
-                                let quad = VoxelQuad::new(pos, texture_index, w, h, signed_axis);
+                                let quad =
+                                    VoxelQuad::new(pos, texture_index, w, h, signed_axis, lighting);
                                 self.quads.push(quad);
                             }
                         }
@@ -261,11 +324,13 @@ impl Mesher {
                                 let vol_x = x << SHIFT_0;
                                 let vol_xyz = vol_x | vol_yz;
 
-                                let voxel_opt = voxels[vol_xyz];
+                                let voxel_opt = voxels.get(vol_xyz);
                                 let voxel = voxel_opt.unwrap();
+                                let lighting = lighting_at(signed_axis, x, y, z);
 
                                 if (upward_column >> x) & 1 != 0
-                                    && voxel_opt == voxels[vol_xyz + STRIDE_1]
+                                    && voxel_opt == voxels.get(vol_xyz + STRIDE_1)
+                                    && lighting == lighting_at(signed_axis, x, y + 1, z)
                                 {
                                     self.upward_merged[vol_x] += 1;
                                     column &= column - 1;
@@ -279,8 +344,9 @@ impl Mesher {
                                         || voxel_opt != {
                                             let vol_x = right << SHIFT_0;
                                             let vol_xyz = vol_x | vol_yz;
-                                            voxels[vol_xyz]
+                                            voxels.get(vol_xyz)
                                         }
+                                        || lighting != lighting_at(signed_axis, right, y, z)
                                     {
                                         break;
                                     }
@@ -299,20 +365,19 @@ impl Mesher {
 
                                 self.upward_merged[vol_x] = 0;
 
-                                let pos = chunk_origin + IVec3::new(x, y, z);
+                                let pos = quad_pos(IVec3::new(x, y, z));
                                 let texture_index = block_library[voxel].textures[signed_axis];
 
-                                let quad = VoxelQuad::new(pos, texture_index, w, h, signed_axis);
+                                let quad =
+                                    VoxelQuad::new(pos, texture_index, w, h, signed_axis, lighting);
                                 self.quads.push(quad);
                             }
                         }
                     }
                 }
             }
-            offsets[index + 1] = self.quads.len() as u32;
+            offsets[first + index + 1] = self.quads.len() as u32;
         }
-
-        VoxelQuadOffsets(offsets)
     }
 
     pub fn mesh(
@@ -320,25 +385,176 @@ impl Mesher {
         chunk: &Chunk,
         chunk_pos: IVec3,
         block_library: &BlockLibrary,
+        light: Option<&ChunkLight>,
     ) -> (&[VoxelQuad], VoxelQuadOffsets) {
         let Chunk {
             voxels,
             opaque_mask,
             transparent_mask,
+            ..
         } = chunk;
 
+        if let Voxels::Uniform(None) = voxels {
+            if let Some(stats) = &mut self.stats {
+                *stats = MesherStats::default();
+            }
+            return (&self.quads, VoxelQuadOffsets([0; 13]));
+        }
+
         let chunk_origin = chunk_origin(chunk_pos);
 
-        self.face_culling(voxels, opaque_mask, transparent_mask);
+        let start = self.stats.is_some().then(Instant::now);
+
+        self.face_culling(voxels, opaque_mask, transparent_mask, block_library);
 
-        let voxel_quad_offsets = self.face_merging(voxels, chunk_origin, block_library);
+        let culled = self.stats.is_some().then(Instant::now);
 
-        (&self.quads, voxel_quad_offsets)
+        let mut offsets = [0; 13];
+        self.face_merging(
+            voxels,
+            chunk_origin,
+            block_library,
+            opaque_mask,
+            light,
+            false,
+            &mut offsets,
+        );
+        self.face_merging(
+            voxels,
+            chunk_origin,
+            block_library,
+            opaque_mask,
+            light,
+            true,
+            &mut offsets,
+        );
+
+        let offsets = VoxelQuadOffsets(offsets);
+
+        if let (Some(start), Some(culled)) = (start, culled) {
+            let faces = [&self.visible_masks, &self.transparent_visible_masks]
+                .into_iter()
+                .flat_map(|masks| masks.values().flatten())
+                .map(|column| column.count_ones())
+                .sum();
+
+            self.stats = Some(MesherStats {
+                faces,
+                quads: self.quads.len() as u32,
+                opaque_quads: enum_map! { signed_axis => offsets.range(signed_axis).len() as u32 },
+                transparent_quads: enum_map! {
+                    signed_axis => offsets.transparent_range(signed_axis).len() as u32
+                },
+                culling: culled - start,
+                merging: culled.elapsed(),
+            });
+        }
+
+        (&self.quads, offsets)
+    }
+}
+
+/// Filled by `Mesher::mesh` when `Mesher::stats` is `Some`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MesherStats {
+    /// Visible faces before merging.
+    pub faces: u32,
+    pub quads: u32,
+    pub opaque_quads: SignedAxisMap<u32>,
+    pub transparent_quads: SignedAxisMap<u32>,
+    pub culling: Duration,
+    pub merging: Duration,
+}
+
+impl MesherStats {
+    /// Visible faces per quad emitted.
+    pub fn merge_ratio(&self) -> f32 {
+        if self.quads == 0 {
+            return 0.0;
+        }
+        self.faces as f32 / self.quads as f32
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.culling + self.merging
+    }
+}
+
+/// Lighting of the face of `(x, y, z)` toward `signed_axis`.
+///
+/// The low byte is corner AO, `0` is darkest, packed as 2 bits per corner
+/// in the order `(-u, -v)`, `(+u, -v)`, `(-u, +v)`, `(+u, +v)` where `u`
+/// and `v` are the width and height axes of the quad. All `3` when
+/// `ambient_occlusion` is `false`. The next byte is the light of the voxel
+/// in front of the face, full sky light when the chunk isn't lit.
+#[inline]
+fn face_lighting(
+    ambient_occlusion: bool,
+    opaque_mask: &Mask,
+    light: Option<&ChunkLight>,
+    signed_axis: SignedAxis,
+    x: usize,
+    y: usize,
+    z: usize,
+) -> u32 {
+    let [nx, ny, nz] = signed_axis.coords();
+    let p = IVec3::new(x as i32 + nx, y as i32 + ny, z as i32 + nz);
+
+    let light = light.map_or(LightChannel::Sky.with(0, MAX_LIGHT), |light| {
+        light.get(p.as_uvec3())
+    }) as u32;
+
+    if !ambient_occlusion {
+        return light << 8 | 0xFF;
+    }
+
+    // must match the width and height axes in `face_merging`
+    let (u, v) = match signed_axis.axis() {
+        Axis::X => (IVec3::Z, IVec3::Y),
+        Axis::Y => (IVec3::X, IVec3::Z),
+        Axis::Z => (IVec3::X, IVec3::Y),
+    };
+
+    let opaque = |p: IVec3| {
+        let area_yz = (p.y as usize) << SHIFT_0 | (p.z as usize) << SHIFT_1;
+        (opaque_mask[area_yz] >> p.x) & 1 != 0
+    };
+
+    let mut ao = 0;
+    for (i, (du, dv)) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].into_iter().enumerate() {
+        let side_u = opaque(p + u * du);
+        let side_v = opaque(p + v * dv);
+        let corner = opaque(p + u * du + v * dv);
+
+        let value = if side_u && side_v {
+            0
+        } else {
+            3 - side_u as u32 - side_v as u32 - corner as u32
+        };
+
+        ao |= value << (i * 2);
     }
+
+    light << 8 | ao
 }
 
 impl Chunk {
     pub fn build_masks(&mut self, block_library: &BlockLibrary) {
+        if let Voxels::Uniform(voxel_opt) = self.voxels {
+            let (opaque, transparent, model) = voxel_opt.map_or((false, false, false), |voxel| {
+                mask_bits(block_library, voxel)
+            });
+
+            self.opaque_mask = Mask::filled(opaque);
+            self.transparent_mask = Mask::filled(transparent);
+            self.model_mask = Mask::filled(model);
+            return;
+        }
+
+        let mut opaque_mask = Box::new([0; AREA]);
+        let mut transparent_mask = Box::new([0; AREA]);
+        let mut model_mask = Box::new([0; AREA]);
+
         for z in 0..LEN {
             let cub_z = z << SHIFT_2;
 
@@ -355,43 +571,71 @@ impl Chunk {
                     let cub_x = x << SHIFT_0;
                     let cub_xyz = cub_x | cub_yz;
 
-                    if let Some(voxel) = self.voxels[cub_xyz] {
-                        let is_transparent = block_library[voxel].is_transparent;
-                        let is_opaque = !is_transparent;
+                    if let Some(voxel) = self.voxels.get(cub_xyz) {
+                        let (is_opaque, is_transparent, is_model) = mask_bits(block_library, voxel);
 
-                        self.opaque_mask[area_yz] |= (is_opaque as u64) << x;
-                        self.transparent_mask[area_yz] |= (is_transparent as u64) << x;
+                        opaque_mask[area_yz] |= (is_opaque as u64) << x;
+                        transparent_mask[area_yz] |= (is_transparent as u64) << x;
+                        model_mask[area_yz] |= (is_model as u64) << x;
                     }
                 }
             }
         }
+
+        self.opaque_mask = Mask::Dense(opaque_mask);
+        self.transparent_mask = Mask::Dense(transparent_mask);
+        self.model_mask = Mask::Dense(model_mask);
+
+        // deep underground every voxel is opaque
+        self.opaque_mask.compact();
+        self.transparent_mask.compact();
+        self.model_mask.compact();
     }
 
     pub fn update_masks(
         &mut self,
         pos: UVec3,
-        voxel_opt: Option<Voxel>,
+        voxel_opt: Option<VoxelIndex>,
         block_library: &BlockLibrary,
     ) {
         let area_y = (pos.y as usize) << SHIFT_0;
         let area_z = (pos.z as usize) << SHIFT_1;
         let area_yz = area_y | area_z;
 
-        let mask = !(1 << pos.x);
-
-        self.opaque_mask[area_yz] &= mask;
-        self.transparent_mask[area_yz] &= mask;
-
-        if let Some(voxel) = voxel_opt {
-            let is_transparent = block_library[voxel].is_transparent;
-            let is_opaque = !is_transparent;
+        let (is_opaque, is_transparent, is_model) = voxel_opt
+            .map_or((false, false, false), |voxel| {
+                mask_bits(block_library, voxel)
+            });
+
+        for (mask, bit) in [
+            (&mut self.opaque_mask, is_opaque),
+            (&mut self.transparent_mask, is_transparent),
+            (&mut self.model_mask, is_model),
+        ] {
+            // uniform masks stay uniform until a bit changes
+            if (mask[area_yz] >> pos.x) & 1 == bit as u64 {
+                continue;
+            }
 
-            self.opaque_mask[area_yz] |= (is_opaque as u64) << pos.x;
-            self.transparent_mask[area_yz] |= (is_transparent as u64) << pos.x;
+            let column = &mut mask.dense_mut()[area_yz];
+            *column = (*column & !(1 << pos.x)) | (bit as u64) << pos.x;
         }
     }
 }
 
+/// Whether `voxel` goes in the opaque, transparent or model mask.
+#[inline]
+fn mask_bits(block_library: &BlockLibrary, voxel: VoxelIndex) -> (bool, bool, bool) {
+    let block = &block_library[voxel];
+    let is_model = block.model.is_some();
+
+    (
+        !is_model && !block.is_transparent,
+        !is_model && block.is_transparent,
+        is_model,
+    )
+}
+
 // This can be aligned to 8 bytes instead of 16 bytes by
 // storing the `ChunkOffset` (`U6Vec3`) and a `chunk_index`
 // (`u16`) that points to a `ChunkPos` (`I26Vec3`) in a
@@ -402,6 +646,8 @@ impl Chunk {
 pub struct VoxelQuad {
     pos: IVec3,
     data: u32,
+    /// { ao: 4 x u2, light: u8, lod: u2 }, see `face_lighting`
+    lighting: u32,
 }
 
 impl VoxelQuad {
@@ -412,6 +658,7 @@ impl VoxelQuad {
         w: u32,
         h: u32,
         signed_axis: SignedAxis,
+        lighting: u32,
     ) -> Self {
         // this must match the shader
         let signed_axis = match signed_axis {
@@ -426,23 +673,98 @@ impl VoxelQuad {
         Self {
             pos,
             data: signed_axis << 28 | h << 22 | w << 16 | texture_index,
+            lighting,
         }
     }
+
+    #[inline]
+    pub const fn pos(&self) -> IVec3 {
+        self.pos
+    }
+
+    #[inline]
+    pub const fn texture_index(&self) -> u32 {
+        self.data & 0xFFFF
+    }
+
+    /// Width and height in voxels of its LOD.
+    #[inline]
+    pub const fn size(&self) -> (u32, u32) {
+        ((self.data >> 16) & 0x3F, (self.data >> 22) & 0x3F)
+    }
+
+    #[inline]
+    pub const fn signed_axis(&self) -> SignedAxis {
+        match self.data >> 28 {
+            0 => PosX,
+            1 => PosY,
+            2 => PosZ,
+            3 => NegX,
+            4 => NegY,
+            _ => NegZ,
+        }
+    }
+
+    #[inline]
+    pub const fn lighting(&self) -> u32 {
+        self.lighting
+    }
+
+    #[inline]
+    pub const fn lod(&self) -> u8 {
+        ((self.lighting >> 16) & 0b11) as u8
+    }
+
+    /// Minimum corner of every voxel whose face it covers.
+    pub fn covered(&self) -> impl Iterator<Item = IVec3> {
+        // must match the width and height axes in `face_merging`
+        let (u, v) = match self.signed_axis().axis() {
+            Axis::X => (IVec3::Z, IVec3::Y),
+            Axis::Y => (IVec3::X, IVec3::Z),
+            Axis::Z => (IVec3::X, IVec3::Y),
+        };
+
+        let (w, h) = self.size();
+        let scale = 1i32 << self.lod();
+        let pos = self.pos;
+
+        (0..h as i32).flat_map(move |j| (0..w as i32).map(move |i| pos + (u * i + v * j) * scale))
+    }
 }
 
-pub struct VoxelQuadOffsets([u32; 7]);
+/// Opaque ranges followed by transparent ranges, each ordered by `SignedAxis`.
+pub struct VoxelQuadOffsets([u32; 13]);
 
 impl VoxelQuadOffsets {
+    /// Opaque quads facing `signed_axis`.
     pub fn range(&self, signed_axis: SignedAxis) -> Range<u32> {
+        self.range_from(0, signed_axis)
+    }
+
+    /// Transparent quads facing `signed_axis`.
+    pub fn transparent_range(&self, signed_axis: SignedAxis) -> Range<u32> {
+        self.range_from(6, signed_axis)
+    }
+
+    /// Every transparent quad, for sorting.
+    pub fn transparent(&self) -> Range<u32> {
+        self.0[6]..self.0[12]
+    }
+
+    #[inline]
+    fn range_from(&self, first: usize, signed_axis: SignedAxis) -> Range<u32> {
         // must match the ordering in `face_merging`
-        match signed_axis {
-            PosX => self.0[0]..self.0[1],
-            PosY => self.0[1]..self.0[2],
-            PosZ => self.0[2]..self.0[3],
-            NegX => self.0[3]..self.0[4],
-            NegY => self.0[4]..self.0[5],
-            NegZ => self.0[5]..self.0[6],
-        }
+        let index = first
+            + match signed_axis {
+                PosX => 0,
+                PosY => 1,
+                PosZ => 2,
+                NegX => 3,
+                NegY => 4,
+                NegZ => 5,
+            };
+
+        self.0[index]..self.0[index + 1]
     }
 
     pub fn shift(&mut self, shift: u32) {
@@ -1,48 +1,113 @@
+pub mod dirty;
 pub mod generator;
+pub mod light;
+pub mod lod;
+pub mod mask;
 pub mod mesher;
+pub mod model_mesher;
+pub mod padding;
+pub mod palette;
 pub mod space;
 pub mod task;
 
-use bevy::prelude::*;
+use bevy::{platform::collections::HashMap, prelude::*};
 use dashmap::DashMap;
-use pad::{AREA, VOL};
 use std::sync::Arc;
 
+pub use dirty::*;
+pub use light::*;
+pub use lod::*;
+pub use mask::*;
 pub use mesher::*;
+pub use model_mesher::*;
+pub use palette::*;
 pub use space::*;
 pub use task::*;
 
 use derive_more::{From, Into};
 use nonmax::NonMaxU16;
 
-use crate::{block_lib::BlockLibrary, render::alloc_buffer::Allocation};
+use crate::{
+    block_lib::{BlockLibrary, remap::BlockRemap},
+    render::alloc_buffer::{AllocBuffer, Allocation},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, From, Into)]
 pub struct VoxelIndex(pub NonMaxU16);
 
+impl VoxelIndex {
+    #[inline]
+    pub fn new(index: usize) -> Option<Self> {
+        u16::try_from(index).ok().and_then(NonMaxU16::new).map(Self)
+    }
+
+    #[inline]
+    pub fn get(self) -> usize {
+        self.0.get() as usize
+    }
+}
+
+#[derive(Clone)]
 pub struct Chunk {
-    voxels: [Option<VoxelIndex>; VOL],
-    some_mask: [u64; AREA],
-    // opaque_mask: [u64; AREA], // implicitly opaque if some & !transparent
-    transparent_mask: [u64; AREA],
+    voxels: Voxels,
+    opaque_mask: Mask,
+    transparent_mask: Mask,
+    /// Voxels meshed by the `ModelMesher`, in neither other mask.
+    model_mask: Mask,
 }
 
 impl Chunk {
     pub const EMPTY: Self = Self {
-        voxels: [None; VOL],
-        opaque_mask: [0; AREA],
-        transparent_mask: [0; AREA],
+        voxels: Voxels::EMPTY,
+        opaque_mask: Mask::EMPTY,
+        transparent_mask: Mask::EMPTY,
+        model_mask: Mask::EMPTY,
     };
 
-    pub fn set(&mut self, pos: UVec3, voxel_opt: Option<Voxel>, block_library: &BlockLibrary) {
+    pub fn set(&mut self, pos: UVec3, voxel_opt: Option<VoxelIndex>, block_library: &BlockLibrary) {
         let index = pad::linearize(pos);
-        self.voxels[index] = voxel_opt;
+        self.voxels.set(index, voxel_opt);
         self.update_masks(pos, voxel_opt, block_library);
     }
 
-    pub fn get(&self, pos: UVec3) -> Option<Voxel> {
+    pub fn get(&self, pos: UVec3) -> Option<VoxelIndex> {
         let index = pad::linearize(pos);
-        self.voxels[index]
+        self.voxels.get(index)
+    }
+
+    pub fn from_voxels(voxels: Voxels, block_library: &BlockLibrary) -> Self {
+        let mut chunk = Self {
+            voxels,
+            ..Self::EMPTY
+        };
+        chunk.build_masks(block_library);
+        chunk
+    }
+
+    pub fn voxels(&self) -> &Voxels {
+        &self.voxels
+    }
+
+    /// Moves voxels from the `BlockLibrary` `remap` was built from to `block_library`.
+    pub fn remap(&mut self, remap: &BlockRemap, block_library: &BlockLibrary) {
+        self.voxels.remap(|voxel_opt| remap.get(voxel_opt));
+        self.build_masks(block_library);
+    }
+
+    /// See `Voxels::compact`, also frees masks that became uniform.
+    pub fn compact(&mut self) {
+        self.voxels.compact();
+        self.opaque_mask.compact();
+        self.transparent_mask.compact();
+        self.model_mask.compact();
+    }
+
+    /// Number of bytes allocated for voxels and masks, excluding `self`.
+    pub fn heap_size(&self) -> usize {
+        self.voxels.heap_size()
+            + self.opaque_mask.heap_size()
+            + self.transparent_mask.heap_size()
+            + self.model_mask.heap_size()
     }
 }
 
@@ -55,7 +120,39 @@ impl Default for Chunk {
 #[derive(Component, Default, Clone, Deref)]
 pub struct ChunkMap(pub Arc<DashMap<ChunkPos, Chunk>>);
 
+impl ChunkMap {
+    pub fn remap(&self, remap: &BlockRemap, block_library: &BlockLibrary) {
+        for mut chunk in self.iter_mut() {
+            chunk.remap(remap, block_library);
+        }
+    }
+}
+
 pub struct ChunkMesh {
     allocation: Allocation<VoxelQuad>,
     offsets: VoxelQuadOffsets,
+    /// `None` without model voxels.
+    model_allocation: Option<Allocation<ModelQuad>>,
+    model_offsets: ModelQuadOffsets,
 }
+
+impl ChunkMesh {
+    pub fn offsets(&self) -> &VoxelQuadOffsets {
+        &self.offsets
+    }
+
+    pub fn model_offsets(&self) -> &ModelQuadOffsets {
+        &self.model_offsets
+    }
+
+    pub fn free(self, alloc_buffer: &AllocBuffer<VoxelQuad>, model_buffer: &AllocBuffer<ModelQuad>) {
+        alloc_buffer.lock().free(self.allocation);
+
+        if let Some(model_allocation) = self.model_allocation {
+            model_buffer.lock().free(model_allocation);
+        }
+    }
+}
+
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct ChunkMeshMap(pub HashMap<ChunkPos, ChunkMesh>);
@@ -0,0 +1,231 @@
+use bevy::math::{IVec3, Vec3};
+use bytemuck::{Pod, Zeroable};
+use std::ops::Range;
+
+use crate::block_lib::BlockLibrary;
+
+use super::{
+    Chunk, ChunkLight, LightChannel, MAX_LIGHT, Voxels, chunk_origin,
+    mesher::UNPADDED_MASK,
+    pad::{LEN, SHIFT_0, SHIFT_1, SHIFT_2},
+};
+
+// Emits a `ModelQuad` per `ModelFace` of every voxel in the `model_mask`,
+// without merging. Faces on a side of the voxel are culled by an opaque
+// neighbour and lit by it, faces inside the voxel are lit by the voxel.
+// Model faces have no AO.
+
+pub struct ModelMesher {
+    /// Quads are scaled by `2^lod`, see `lod.rs`.
+    pub lod: u8,
+    quads: Vec<ModelQuad>,
+    transparent_quads: Vec<ModelQuad>,
+}
+
+impl ModelMesher {
+    pub fn new() -> Self {
+        Self {
+            lod: 0,
+            quads: Vec::new(),
+            transparent_quads: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.quads.clear();
+        self.transparent_quads.clear();
+    }
+
+    pub fn mesh(
+        &mut self,
+        chunk: &Chunk,
+        chunk_pos: IVec3,
+        block_library: &BlockLibrary,
+        light: Option<&ChunkLight>,
+    ) -> (&[ModelQuad], ModelQuadOffsets) {
+        let Chunk {
+            voxels,
+            opaque_mask,
+            model_mask,
+            ..
+        } = chunk;
+
+        if let Voxels::Uniform(None) = voxels {
+            return (&self.quads, ModelQuadOffsets([0; 3]));
+        }
+
+        let chunk_origin = chunk_origin(chunk_pos);
+        let scale = 1i32 << self.lod;
+        let lod = (self.lod as u32) << 16;
+
+        let opaque = |p: IVec3| {
+            let area_yz = (p.y as usize) << SHIFT_0 | (p.z as usize) << SHIFT_1;
+            (opaque_mask[area_yz] >> p.x) & 1 != 0
+        };
+
+        let light_at = |p: IVec3| {
+            light.map_or(LightChannel::Sky.with(0, MAX_LIGHT), |light| {
+                light.get(p.as_uvec3())
+            }) as u32
+        };
+
+        for z in 1..LEN - 1 {
+            let vol_z = z << SHIFT_2;
+
+            let area_z = z << SHIFT_1;
+
+            for y in 1..LEN - 1 {
+                let vol_y = y << SHIFT_1;
+                let vol_yz = vol_y | vol_z;
+
+                let area_y = y << SHIFT_0;
+                let area_yz = area_y | area_z;
+
+                let mut column = model_mask[area_yz] & UNPADDED_MASK;
+
+                while column != 0 {
+                    let x = column.trailing_zeros() as usize;
+                    column &= column - 1;
+
+                    let vol_x = x << SHIFT_0;
+                    let vol_xyz = vol_x | vol_yz;
+
+                    let block = &block_library[voxels.get(vol_xyz).unwrap()];
+                    let Some(faces) = &block.model else {
+                        continue;
+                    };
+
+                    let local = IVec3::new(x as i32, y as i32, z as i32);
+                    // must match `quad_pos` in `Mesher::face_merging`
+                    let pos = (chunk_origin + local - IVec3::ONE) * scale + IVec3::ONE;
+
+                    let quads = if block.is_transparent {
+                        &mut self.transparent_quads
+                    } else {
+                        &mut self.quads
+                    };
+
+                    for face in faces.iter() {
+                        let lit_from = match face.cull {
+                            Some(signed_axis) => {
+                                let adj = local + IVec3::from_array(signed_axis.coords());
+                                if opaque(adj) {
+                                    continue;
+                                }
+                                adj
+                            }
+                            None => local,
+                        };
+
+                        let lighting = lod | light_at(lit_from) << 8 | 0xFF;
+
+                        quads.push(ModelQuad::new(
+                            pos,
+                            face.origin * scale as f32,
+                            face.u * scale as f32,
+                            face.v * scale as f32,
+                            face.uv,
+                            face.texture_index,
+                            lighting,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let transparent_start = self.quads.len() as u32;
+        self.quads.append(&mut self.transparent_quads);
+
+        let offsets = ModelQuadOffsets([0, transparent_start, self.quads.len() as u32]);
+
+        (&self.quads, offsets)
+    }
+}
+
+/// A quad anywhere in a voxel, drawn instanced like `VoxelQuad`.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ModelQuad {
+    pos: IVec3,
+    texture_index: u32,
+    /// Relative to `pos`, scaled by the LOD like `u` and `v`.
+    origin: Vec3,
+    /// { ao: 4 x u2, light: u8, lod: u2 }, see `face_lighting`
+    lighting: u32,
+    u: Vec3,
+    /// { min_u, min_v, max_u, max_v: 4 x u8 } in sixteenths
+    uv: u32,
+    v: Vec3,
+    _padding: u32,
+}
+
+impl ModelQuad {
+    #[inline]
+    pub fn new(
+        pos: IVec3,
+        origin: Vec3,
+        u: Vec3,
+        v: Vec3,
+        uv: [u8; 4],
+        texture_index: u32,
+        lighting: u32,
+    ) -> Self {
+        Self {
+            pos,
+            texture_index,
+            origin,
+            lighting,
+            u,
+            uv: u32::from_le_bytes(uv),
+            v,
+            _padding: 0,
+        }
+    }
+
+    #[inline]
+    pub const fn pos(&self) -> IVec3 {
+        self.pos
+    }
+
+    #[inline]
+    pub const fn texture_index(&self) -> u32 {
+        self.texture_index
+    }
+
+    /// `origin`, `u` and `v`, the quad covers `pos + origin + s * u + t * v`
+    /// for `s` and `t` in `0..=1`.
+    #[inline]
+    pub const fn span(&self) -> (Vec3, Vec3, Vec3) {
+        (self.origin, self.u, self.v)
+    }
+
+    #[inline]
+    pub const fn uv(&self) -> [u8; 4] {
+        self.uv.to_le_bytes()
+    }
+
+    #[inline]
+    pub const fn lighting(&self) -> u32 {
+        self.lighting
+    }
+}
+
+/// Opaque quads followed by transparent quads.
+pub struct ModelQuadOffsets([u32; 3]);
+
+impl ModelQuadOffsets {
+    pub fn opaque(&self) -> Range<u32> {
+        self.0[0]..self.0[1]
+    }
+
+    /// Every transparent quad, for sorting.
+    pub fn transparent(&self) -> Range<u32> {
+        self.0[1]..self.0[2]
+    }
+
+    pub fn shift(&mut self, shift: u32) {
+        for offset in &mut self.0 {
+            *offset += shift
+        }
+    }
+}
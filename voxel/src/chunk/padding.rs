@@ -0,0 +1,297 @@
+use bevy::{
+    math::{IVec3, UVec3},
+    platform::collections::HashMap,
+};
+
+use crate::{block_lib::BlockLibrary, history::VoxelChange};
+
+use super::{
+    ChunkMap, ChunkPos, DirtyChunks, VoxelIndex, chunk_origin, pad, padded_chunks, unpad,
+    voxel_chunk_pos, voxel_local_pos,
+};
+
+// The padding of a chunk mirrors the outermost unpadded layer of its 26
+// neighbours. Each neighbour `chunk_pos + offset` owns the region of the
+// padding toward `offset`, a face, edge or corner, and a padded position
+// `pos` in that region is `pos - offset * unpad::LEN` inside the neighbour.
+
+/// Every chunk sharing padding with a chunk, as offsets.
+pub const NEIGHBOURS: [IVec3; 26] = {
+    let mut neighbours = [IVec3::ZERO; 26];
+    let mut i = 0;
+    let mut index = 0;
+    while index < 27 {
+        let offset = IVec3::new(index % 3 - 1, index / 3 % 3 - 1, index / 9 - 1);
+        if index != 13 {
+            neighbours[i] = offset;
+            i += 1;
+        }
+        index += 1;
+    }
+    neighbours
+};
+
+/// Padded positions in the region of the padding toward `offset`.
+pub fn padding_region(offset: IVec3) -> impl Iterator<Item = UVec3> {
+    let range = |c: i32| match c.signum() {
+        -1 => 0..=0,
+        0 => 1..=unpad::LEN as u32,
+        _ => pad::LEN as u32 - 1..=pad::LEN as u32 - 1,
+    };
+
+    let [rx, ry, rz] = offset.to_array().map(range);
+
+    rz.flat_map(move |z| {
+        let rx = rx.clone();
+        ry.clone()
+            .flat_map(move |y| rx.clone().map(move |x| UVec3::new(x, y, z)))
+    })
+}
+
+/// `pos` in the padding toward `offset` as a position inside the neighbour at `offset`.
+#[inline]
+pub fn to_neighbour(pos: UVec3, offset: IVec3) -> UVec3 {
+    (pos.as_ivec3() - offset * unpad::LEN as i32).as_uvec3()
+}
+
+impl ChunkMap {
+    /// The voxel at `voxel_pos`, `None` if its chunk isn't loaded.
+    pub fn get_voxel(&self, voxel_pos: IVec3) -> Option<Option<VoxelIndex>> {
+        let chunk = self.get(&voxel_chunk_pos(voxel_pos))?;
+        Some(chunk.get(voxel_local_pos(voxel_pos)))
+    }
+
+    /// Sets the voxel at `voxel_pos` and mirrors it into the padding of loaded neighbours,
+    /// marking every chunk that changed.
+    ///
+    /// Returns `false` if the chunk containing `voxel_pos` isn't loaded.
+    pub fn set_voxel(
+        &self,
+        voxel_pos: IVec3,
+        voxel_opt: Option<VoxelIndex>,
+        block_library: &BlockLibrary,
+        dirty_chunks: &mut DirtyChunks,
+        mut changes: Option<&mut Vec<VoxelChange>>,
+    ) -> bool {
+        for (index, (chunk_pos, pos)) in padded_chunks(voxel_pos).enumerate() {
+            let Some(mut chunk) = self.get_mut(&chunk_pos) else {
+                if index == 0 {
+                    return false;
+                }
+                continue;
+            };
+
+            let old = chunk.get(pos);
+            if old != voxel_opt {
+                chunk.set(pos, voxel_opt, block_library);
+                dirty_chunks.mark(chunk_pos);
+
+                if index == 0
+                    && let Some(changes) = &mut changes
+                {
+                    changes.push(VoxelChange::new(chunk_pos, pos, old, voxel_opt));
+                }
+            }
+        }
+
+        true
+    }
+
+    /// `set_voxel` for many voxels, locking each chunk once.
+    ///
+    /// Voxels whose chunk isn't loaded are skipped. Returns how many were written.
+    pub fn set_voxels(
+        &self,
+        voxels: impl IntoIterator<Item = (IVec3, Option<VoxelIndex>)>,
+        block_library: &BlockLibrary,
+        dirty_chunks: &mut DirtyChunks,
+        mut changes: Option<&mut Vec<VoxelChange>>,
+    ) -> usize {
+        let mut loaded = HashMap::<ChunkPos, bool>::new();
+        // `true` for writes to the chunk owning the voxel rather than its padding
+        let mut writes = HashMap::<ChunkPos, Vec<(UVec3, Option<VoxelIndex>, bool)>>::new();
+        let mut count = 0;
+
+        for (voxel_pos, voxel_opt) in voxels {
+            let chunk_pos = voxel_chunk_pos(voxel_pos);
+            if !*loaded
+                .entry(chunk_pos)
+                .or_insert_with(|| self.contains_key(&chunk_pos))
+            {
+                continue;
+            }
+
+            count += 1;
+
+            for (index, (chunk_pos, pos)) in padded_chunks(voxel_pos).enumerate() {
+                writes
+                    .entry(chunk_pos)
+                    .or_default()
+                    .push((pos, voxel_opt, index == 0));
+            }
+        }
+
+        for (chunk_pos, writes) in writes {
+            let Some(mut chunk) = self.get_mut(&chunk_pos) else {
+                continue;
+            };
+
+            let mut changed = false;
+            for (pos, voxel_opt, owned) in writes {
+                let old = chunk.get(pos);
+                if old != voxel_opt {
+                    chunk.set(pos, voxel_opt, block_library);
+                    changed = true;
+
+                    if owned && let Some(changes) = &mut changes {
+                        changes.push(VoxelChange::new(chunk_pos, pos, old, voxel_opt));
+                    }
+                }
+            }
+
+            if changed {
+                dirty_chunks.mark(chunk_pos);
+            }
+        }
+
+        count
+    }
+
+    /// Loaded voxels in `min..=max` for which `f` returns `true`.
+    pub fn find_voxels(
+        &self,
+        min: IVec3,
+        max: IVec3,
+        mut f: impl FnMut(Option<VoxelIndex>) -> bool,
+    ) -> Vec<IVec3> {
+        let mut found = Vec::new();
+
+        self.for_each_voxel(min, max, |voxel_pos, voxel_opt| {
+            if f(voxel_opt) {
+                found.push(voxel_pos);
+            }
+        });
+
+        found
+    }
+
+    /// Calls `f` with every loaded voxel in `min..=max`, locking each chunk once.
+    pub fn for_each_voxel(
+        &self,
+        min: IVec3,
+        max: IVec3,
+        mut f: impl FnMut(IVec3, Option<VoxelIndex>),
+    ) {
+        let min_chunk = voxel_chunk_pos(min);
+        let max_chunk = voxel_chunk_pos(max);
+
+        for z in min_chunk.z..=max_chunk.z {
+            for y in min_chunk.y..=max_chunk.y {
+                for x in min_chunk.x..=max_chunk.x {
+                    let chunk_pos = IVec3::new(x, y, z);
+                    let Some(chunk) = self.get(&chunk_pos) else {
+                        continue;
+                    };
+
+                    let origin = chunk_origin(chunk_pos);
+                    let local_min = (min - origin).max(IVec3::ONE);
+                    let local_max = (max - origin).min(IVec3::splat(unpad::LEN as i32));
+
+                    for lz in local_min.z..=local_max.z {
+                        for ly in local_min.y..=local_max.y {
+                            for lx in local_min.x..=local_max.x {
+                                let local = IVec3::new(lx, ly, lz);
+                                f(origin + local, chunk.get(local.as_uvec3()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Copies the border of every loaded neighbour into the padding of `chunk_pos`.
+    ///
+    /// Returns `true` if the padding changed.
+    pub fn fill_padding(&self, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> bool {
+        let mut writes = Vec::new();
+
+        for offset in NEIGHBOURS {
+            let Some(neighbour) = self.get(&(chunk_pos + offset)) else {
+                continue;
+            };
+
+            writes.extend(
+                padding_region(offset).map(|pos| (pos, neighbour.get(to_neighbour(pos, offset)))),
+            );
+        }
+
+        let Some(mut chunk) = self.get_mut(&chunk_pos) else {
+            return false;
+        };
+
+        let mut changed = false;
+        for (pos, voxel_opt) in writes {
+            if chunk.get(pos) != voxel_opt {
+                chunk.set(pos, voxel_opt, block_library);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Copies the border of `chunk_pos` into the padding of every loaded neighbour.
+    ///
+    /// Returns the neighbours whose padding changed.
+    pub fn share_padding(
+        &self,
+        chunk_pos: ChunkPos,
+        block_library: &BlockLibrary,
+    ) -> Vec<ChunkPos> {
+        let mut changed = Vec::new();
+
+        for offset in NEIGHBOURS {
+            let neighbour_pos = chunk_pos + offset;
+
+            if !self.contains_key(&neighbour_pos) {
+                continue;
+            }
+
+            // the neighbour's padding toward -offset holds this chunk's border
+            let writes = {
+                let Some(chunk) = self.get(&chunk_pos) else {
+                    return changed;
+                };
+
+                padding_region(-offset)
+                    .map(|pos| (pos, chunk.get(to_neighbour(pos, -offset))))
+                    .collect::<Vec<_>>()
+            };
+
+            let Some(mut neighbour) = self.get_mut(&neighbour_pos) else {
+                continue;
+            };
+
+            let mut neighbour_changed = false;
+            for (pos, voxel_opt) in writes {
+                if neighbour.get(pos) != voxel_opt {
+                    neighbour.set(pos, voxel_opt, block_library);
+                    neighbour_changed = true;
+                }
+            }
+
+            if neighbour_changed {
+                changed.push(neighbour_pos);
+            }
+        }
+
+        changed
+    }
+
+    /// `fill_padding` then `share_padding`, for a chunk that was just inserted.
+    pub fn link_padding(&self, chunk_pos: ChunkPos, block_library: &BlockLibrary) -> Vec<ChunkPos> {
+        self.fill_padding(chunk_pos, block_library);
+        self.share_padding(chunk_pos, block_library)
+    }
+}
@@ -0,0 +1,239 @@
+use super::{VoxelIndex, pad::VOL};
+
+// Voxels are stored as indices into a palette of `Option<VoxelIndex>`
+// packed into `u64` words. Index width is always a power of two
+// (1, 2, 4, 8, 16) so an index never straddles two words.
+// A chunk holding a single value stores no words at all.
+
+const MAX_BITS: u32 = 16;
+
+#[derive(Debug, Clone)]
+pub enum Voxels {
+    Uniform(Option<VoxelIndex>),
+    Paletted(PalettedVoxels),
+}
+
+impl Voxels {
+    pub const EMPTY: Self = Self::Uniform(None);
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<VoxelIndex> {
+        match self {
+            Self::Uniform(voxel_opt) => *voxel_opt,
+            Self::Paletted(paletted) => paletted.get(index),
+        }
+    }
+
+    pub fn set(&mut self, index: usize, voxel_opt: Option<VoxelIndex>) {
+        match self {
+            Self::Uniform(uniform) => {
+                if *uniform == voxel_opt {
+                    return;
+                }
+
+                let mut paletted = PalettedVoxels::uniform(*uniform);
+                paletted.set(index, voxel_opt);
+                *self = Self::Paletted(paletted);
+            }
+            Self::Paletted(paletted) => paletted.set(index, voxel_opt),
+        }
+    }
+
+    pub fn fill(&mut self, voxel_opt: Option<VoxelIndex>) {
+        *self = Self::Uniform(voxel_opt);
+    }
+
+    pub fn uniform(&self) -> Option<Option<VoxelIndex>> {
+        match self {
+            Self::Uniform(voxel_opt) => Some(*voxel_opt),
+            Self::Paletted(_) => None,
+        }
+    }
+
+    /// Maps every stored value through `f` by rewriting the palette only.
+    pub fn remap(&mut self, f: impl Fn(Option<VoxelIndex>) -> Option<VoxelIndex>) {
+        match self {
+            Self::Uniform(voxel_opt) => *voxel_opt = f(*voxel_opt),
+            Self::Paletted(paletted) => {
+                for voxel_opt in &mut paletted.palette {
+                    *voxel_opt = f(*voxel_opt);
+                }
+            }
+        }
+    }
+
+    /// Drops unused palette entries, shrinking the index width
+    /// and falling back to `Uniform` when only one value remains.
+    pub fn compact(&mut self) {
+        let Self::Paletted(paletted) = self else {
+            return;
+        };
+
+        let mut used = vec![false; paletted.palette.len()];
+        for index in 0..VOL {
+            used[paletted.get_raw(index)] = true;
+        }
+
+        let used_count = used.iter().filter(|u| **u).count();
+
+        if used_count == 1 {
+            let raw = used.iter().position(|u| *u).unwrap();
+            *self = Self::Uniform(paletted.palette[raw]);
+            return;
+        }
+
+        if used_count == paletted.palette.len() && bits_for(used_count) == paletted.bits {
+            return;
+        }
+
+        let mut compacted = PalettedVoxels::with_bits(bits_for(used_count));
+        for index in 0..VOL {
+            compacted.set(index, paletted.get(index));
+        }
+        *paletted = compacted;
+    }
+
+    /// Number of bytes allocated for voxel storage, excluding `self`.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Self::Uniform(_) => 0,
+            Self::Paletted(paletted) => {
+                paletted.words.len() * size_of::<u64>()
+                    + paletted.palette.capacity() * size_of::<Option<VoxelIndex>>()
+            }
+        }
+    }
+}
+
+impl Default for Voxels {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PalettedVoxels {
+    palette: Vec<Option<VoxelIndex>>,
+    bits: u32,
+    words: Box<[u64]>,
+}
+
+impl PalettedVoxels {
+    fn with_bits(bits: u32) -> Self {
+        Self {
+            palette: Vec::new(),
+            bits,
+            words: vec![0; VOL * bits as usize / 64].into_boxed_slice(),
+        }
+    }
+
+    fn uniform(voxel_opt: Option<VoxelIndex>) -> Self {
+        let mut paletted = Self::with_bits(1);
+        // every index is already `0`
+        paletted.palette.push(voxel_opt);
+        paletted
+    }
+
+    /// Rebuilds storage from its raw parts, returning `None` if they are inconsistent.
+    pub fn from_parts(
+        palette: Vec<Option<VoxelIndex>>,
+        bits: u32,
+        words: Box<[u64]>,
+    ) -> Option<Self> {
+        if !bits.is_power_of_two()
+            || bits > MAX_BITS
+            || words.len() != VOL * bits as usize / 64
+            || palette.is_empty()
+            || palette.len() > 1 << bits
+        {
+            return None;
+        }
+
+        let paletted = Self {
+            palette,
+            bits,
+            words,
+        };
+
+        (0..VOL)
+            .all(|index| paletted.get_raw(index) < paletted.palette.len())
+            .then_some(paletted)
+    }
+
+    #[inline]
+    pub fn palette(&self) -> &[Option<VoxelIndex>] {
+        &self.palette
+    }
+
+    #[inline]
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<VoxelIndex> {
+        self.palette[self.get_raw(index)]
+    }
+
+    pub fn set(&mut self, index: usize, voxel_opt: Option<VoxelIndex>) {
+        let raw = self.palette_index(voxel_opt);
+        self.set_raw(index, raw);
+    }
+
+    #[inline]
+    pub fn get_raw(&self, index: usize) -> usize {
+        let bit = index * self.bits as usize;
+        let word = self.words[bit >> 6];
+        let mask = (1 << self.bits) - 1;
+
+        ((word >> (bit & 63)) & mask) as usize
+    }
+
+    #[inline]
+    pub fn set_raw(&mut self, index: usize, raw: usize) {
+        let bit = index * self.bits as usize;
+        let word = &mut self.words[bit >> 6];
+        let shift = bit & 63;
+        let mask = ((1 << self.bits) - 1) << shift;
+
+        *word = (*word & !mask) | ((raw as u64) << shift);
+    }
+
+    fn palette_index(&mut self, voxel_opt: Option<VoxelIndex>) -> usize {
+        if let Some(raw) = self.palette.iter().position(|p| *p == voxel_opt) {
+            return raw;
+        }
+
+        let raw = self.palette.len();
+        if raw == 1 << self.bits {
+            self.grow();
+        }
+
+        self.palette.push(voxel_opt);
+        raw
+    }
+
+    fn grow(&mut self) {
+        let bits = self.bits * 2;
+        debug_assert!(bits <= MAX_BITS);
+
+        let mut grown = Self::with_bits(bits);
+        for index in 0..VOL {
+            grown.set_raw(index, self.get_raw(index));
+        }
+
+        self.bits = bits;
+        self.words = grown.words;
+    }
+}
+
+#[inline]
+fn bits_for(palette_len: usize) -> u32 {
+    let needed = usize::BITS - (palette_len - 1).leading_zeros();
+    needed.next_power_of_two().clamp(1, MAX_BITS)
+}
@@ -64,7 +64,7 @@ pub mod unpad {
     }
 }
 
-use bevy::math::IVec3;
+use bevy::math::{IVec3, UVec3, Vec3};
 
 pub type ChunkPos = IVec3;
 
@@ -72,3 +72,43 @@ pub type ChunkPos = IVec3;
 pub const fn chunk_origin(chunk_pos: ChunkPos) -> IVec3 {
     chunk_pos.wrapping_mul(IVec3::splat(unpad::LEN as i32))
 }
+
+/// Chunk whose unpadded region contains `voxel_pos`.
+#[inline]
+pub fn voxel_chunk_pos(voxel_pos: IVec3) -> ChunkPos {
+    (voxel_pos - IVec3::ONE).div_euclid(IVec3::splat(unpad::LEN as i32))
+}
+
+/// Padded position of `voxel_pos` inside `voxel_chunk_pos(voxel_pos)`.
+#[inline]
+pub fn voxel_local_pos(voxel_pos: IVec3) -> UVec3 {
+    ((voxel_pos - IVec3::ONE).rem_euclid(IVec3::splat(unpad::LEN as i32)) + IVec3::ONE).as_uvec3()
+}
+
+/// Chunk containing a point in the local space of a terrain.
+#[inline]
+pub fn point_chunk_pos(point: Vec3) -> ChunkPos {
+    voxel_chunk_pos(point.floor().as_ivec3())
+}
+
+/// Every chunk whose padded volume contains `voxel_pos`, with the padded position inside it.
+///
+/// The first item is always `voxel_chunk_pos(voxel_pos)`.
+pub fn padded_chunks(voxel_pos: IVec3) -> impl Iterator<Item = (ChunkPos, UVec3)> {
+    let chunk_pos = voxel_chunk_pos(voxel_pos);
+
+    let center = std::iter::once(IVec3::ZERO);
+    let neighbours = (-1..=1)
+        .flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| IVec3::new(x, y, z))))
+        .filter(|offset| *offset != IVec3::ZERO);
+
+    center.chain(neighbours).filter_map(move |offset| {
+        let neighbour = chunk_pos + offset;
+        let local = voxel_pos - chunk_origin(neighbour);
+
+        let inside =
+            local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(pad::LEN as i32)).all();
+
+        inside.then(|| (neighbour, local.as_uvec3()))
+    })
+}
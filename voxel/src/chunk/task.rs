@@ -1,60 +1,153 @@
+use anyhow::Result;
 use bevy::{
+    platform::collections::HashMap,
+    prelude::*,
     render::renderer::{RenderDevice, RenderQueue},
     tasks::{AsyncComputeTaskPool, Task, block_on, poll_once},
 };
 use std::cell::RefCell;
 
-use crate::{block_lib::BlockLibrary, chunk::mesher::VoxelQuad, render::alloc_buffer::AllocBuffer};
+use crate::{
+    block_lib::BlockLibrary, chunk::mesher::VoxelQuad, render::alloc_buffer::AllocBuffer,
+    save::ChunkStorage,
+};
 
-use super::{ChunkMap, ChunkMesh, ChunkPos, Mesher};
+use super::{
+    Chunk, ChunkLight, ChunkMap, ChunkMesh, ChunkPos, LightMap, Mesher, ModelMesher, ModelQuad,
+    generator::TerrainGenerator,
+};
 
 thread_local! {
     static MESHER: RefCell<Mesher> = RefCell::new(Mesher::new());
+    static MODEL_MESHER: RefCell<ModelMesher> = RefCell::new(ModelMesher::new());
+}
+
+/// Meshes `chunk` with both meshers and stores the quads, models only
+/// when there are any.
+pub(super) fn mesh_chunk(
+    chunk: &Chunk,
+    chunk_pos: ChunkPos,
+    lod: u8,
+    light: Option<&ChunkLight>,
+
+    alloc_buffer: &AllocBuffer<VoxelQuad>,
+    model_buffer: &AllocBuffer<ModelQuad>,
+
+    block_library: &BlockLibrary,
+    settings: MeshingSettings,
+
+    queue: &RenderQueue,
+    device: &RenderDevice,
+) -> ChunkMesh {
+    let (allocation, offsets) = MESHER.with_borrow_mut(|mesher| {
+        mesher.clear();
+        mesher.ambient_occlusion = settings.ambient_occlusion;
+        mesher.lod = lod;
+        let (quads, mut offsets) = mesher.mesh(chunk, chunk_pos, block_library, light);
+        let allocation = alloc_buffer.lock().store(quads, queue, device);
+
+        offsets.shift(allocation.offset());
+
+        (allocation, offsets)
+    });
+
+    let (model_allocation, model_offsets) = MODEL_MESHER.with_borrow_mut(|model_mesher| {
+        model_mesher.clear();
+        model_mesher.lod = lod;
+        let (quads, mut offsets) = model_mesher.mesh(chunk, chunk_pos, block_library, light);
+        if quads.is_empty() {
+            return (None, offsets);
+        }
+
+        let allocation = model_buffer.lock().store(quads, queue, device);
+
+        offsets.shift(allocation.offset());
+
+        (Some(allocation), offsets)
+    });
+
+    ChunkMesh {
+        allocation,
+        offsets,
+        model_allocation,
+        model_offsets,
+    }
 }
 
-struct MeshingTasks {
-    tasks: Vec<(ChunkPos, Task<Option<ChunkMesh>>)>,
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct MeshingSettings {
+    /// See `Mesher::ambient_occlusion`.
+    pub ambient_occlusion: bool,
+}
+
+impl Default for MeshingSettings {
+    fn default() -> Self {
+        Self {
+            ambient_occlusion: true,
+        }
+    }
+}
+
+/// In flight meshing, at most one task per `ChunkPos` so results arrive in order.
+#[derive(Component, Default)]
+pub struct MeshingTasks {
+    tasks: HashMap<ChunkPos, Task<Option<ChunkMesh>>>,
 }
 
 impl MeshingTasks {
+    /// Returns `false` if `chunk_pos` is already being meshed.
     pub fn spawn_task(
         &mut self,
         chunk_map: ChunkMap,
+        light_map: LightMap,
         chunk_pos: ChunkPos,
 
         alloc_buffer: AllocBuffer<VoxelQuad>,
+        model_buffer: AllocBuffer<ModelQuad>,
 
         block_library: BlockLibrary,
+        settings: MeshingSettings,
 
         queue: RenderQueue,
         device: RenderDevice,
-    ) {
+    ) -> bool {
+        if self.tasks.contains_key(&chunk_pos) {
+            return false;
+        }
+
         let pool = AsyncComputeTaskPool::get();
 
         let task = pool.spawn(async move {
-            MESHER.with_borrow_mut(|mesher| {
-                let Some(chunk) = chunk_map.get(&chunk_pos) else {
-                    return None;
-                };
-
-                mesher.clear();
-                let (quads, mut offsets) = mesher.mesh(&chunk, chunk_pos, &block_library);
-                let allocation = alloc_buffer.lock().store(quads, &queue, &device);
-
-                offsets.shift(allocation.offset());
-
-                Some(ChunkMesh {
-                    allocation,
-                    offsets,
-                })
-            })
+            let chunk = chunk_map.get(&chunk_pos)?;
+
+            // cloned so lighting isn't blocked while meshing
+            let light = light_map.get(&chunk_pos).map(|light| light.clone());
+
+            Some(mesh_chunk(
+                &chunk,
+                chunk_pos,
+                0,
+                light.as_ref(),
+                &alloc_buffer,
+                &model_buffer,
+                &block_library,
+                settings,
+                &queue,
+                &device,
+            ))
         });
 
-        self.tasks.push((chunk_pos, task));
+        self.tasks.insert(chunk_pos, task);
+
+        true
+    }
+
+    pub fn contains(&self, chunk_pos: ChunkPos) -> bool {
+        self.tasks.contains_key(&chunk_pos)
     }
 
-    pub fn poll(&mut self, callback: impl Fn(ChunkPos, Option<ChunkMesh>)) {
-        self.tasks.retain_mut(|(chunk_pos, task)| {
+    pub fn poll(&mut self, mut callback: impl FnMut(ChunkPos, Option<ChunkMesh>)) {
+        self.tasks.retain(|chunk_pos, task| {
             if let Some(mesh_opt) = block_on(poll_once(task)) {
                 callback(*chunk_pos, mesh_opt);
                 false
@@ -64,3 +157,136 @@ impl MeshingTasks {
         });
     }
 }
+
+/// In flight chunk generation, at most one task per `ChunkPos`.
+#[derive(Component, Default)]
+pub struct GenerationTasks {
+    /// `true` for chunks loaded from a `ChunkStorage`.
+    tasks: HashMap<ChunkPos, Task<(Chunk, bool)>>,
+}
+
+impl GenerationTasks {
+    /// Returns `false` if `chunk_pos` is already being generated.
+    pub fn spawn_task(
+        &mut self,
+        chunk_pos: ChunkPos,
+        generator: TerrainGenerator,
+        block_library: BlockLibrary,
+    ) -> bool {
+        if self.tasks.contains_key(&chunk_pos) {
+            return false;
+        }
+
+        let pool = AsyncComputeTaskPool::get();
+
+        let task = pool.spawn(async move { generator.load(chunk_pos, &block_library) });
+
+        self.tasks.insert(chunk_pos, task);
+
+        true
+    }
+
+    pub fn contains(&self, chunk_pos: ChunkPos) -> bool {
+        self.tasks.contains_key(&chunk_pos)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Dropping a `Task` cancels it.
+    pub fn cancel(&mut self, chunk_pos: ChunkPos) -> bool {
+        self.tasks.remove(&chunk_pos).is_some()
+    }
+
+    /// Cancels every task whose `ChunkPos` fails `keep`.
+    pub fn retain(&mut self, mut keep: impl FnMut(ChunkPos) -> bool) {
+        self.tasks.retain(|chunk_pos, _| keep(*chunk_pos));
+    }
+
+    /// Cancels every task and spawns it again with `block_library`, whose
+    /// `VoxelIndex`es the finished chunks will use.
+    pub fn respawn(&mut self, generator: &TerrainGenerator, block_library: &BlockLibrary) {
+        let chunks = self
+            .tasks
+            .drain()
+            .map(|(chunk_pos, _)| chunk_pos)
+            .collect::<Vec<_>>();
+        for chunk_pos in chunks {
+            self.spawn_task(chunk_pos, generator.clone(), block_library.clone());
+        }
+    }
+
+    /// Calls `callback` with every finished chunk and whether it was stored.
+    pub fn poll(&mut self, mut callback: impl FnMut(ChunkPos, Chunk, bool)) {
+        self.tasks.retain(|chunk_pos, task| {
+            if let Some((chunk, stored)) = block_on(poll_once(task)) {
+                callback(*chunk_pos, chunk, stored);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+/// In flight saving of unloaded chunks, at most one task per `ChunkPos`.
+#[derive(Component, Default)]
+pub struct SaveTasks {
+    tasks: HashMap<ChunkPos, Task<(Chunk, Result<()>)>>,
+}
+
+impl SaveTasks {
+    /// Returns `false` if `chunk_pos` is already being saved.
+    pub fn spawn_task(
+        &mut self,
+        chunk_pos: ChunkPos,
+        chunk: Chunk,
+        storage: ChunkStorage,
+        block_library: BlockLibrary,
+    ) -> bool {
+        if self.tasks.contains_key(&chunk_pos) {
+            return false;
+        }
+
+        let pool = AsyncComputeTaskPool::get();
+
+        let task = pool.spawn(async move {
+            let saved = storage.save_chunk(chunk_pos, &chunk, &block_library);
+            (chunk, saved)
+        });
+
+        self.tasks.insert(chunk_pos, task);
+
+        true
+    }
+
+    pub fn contains(&self, chunk_pos: ChunkPos) -> bool {
+        self.tasks.contains_key(&chunk_pos)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Calls `callback` with every saved chunk, handing the chunk back so
+    /// it isn't lost if saving failed.
+    pub fn poll(&mut self, mut callback: impl FnMut(ChunkPos, Chunk, Result<()>)) {
+        self.tasks.retain(|chunk_pos, task| {
+            if let Some((chunk, saved)) = block_on(poll_once(task)) {
+                callback(*chunk_pos, chunk, saved);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
@@ -0,0 +1,281 @@
+use bevy::{
+    ecs::system::SystemParam,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+
+use crate::{
+    block_lib::{BlockLibrary, remap::BlockRemap},
+    chunk::{ChunkMap, ChunkPos, DirtyChunks, LightUpdates, VoxelIndex, unpad},
+    history::{EditHistory, VoxelChange},
+};
+
+// Every operation takes positions in the local voxel space of a terrain,
+// skips voxels whose chunk isn't loaded and writes one `ChunkChanged`
+// per chunk it changed, padding included. Changes are recorded in the
+// terrain's `EditHistory`, queued in its `LightUpdates` and the chunks
+// kept in its `EditedChunks` to be saved when they unload.
+
+/// Written once per chunk changed by a `TerrainEditor` operation.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ChunkChanged {
+    pub terrain: Entity,
+    pub chunk_pos: ChunkPos,
+}
+
+/// Chunks changed by a `TerrainEditor` since they were loaded or saved.
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct EditedChunks {
+    #[deref]
+    pub chunks: HashSet<ChunkPos>,
+    /// Border voxels of each chunk as they were before its first edit.
+    original: HashMap<ChunkPos, HashMap<UVec3, Option<VoxelIndex>>>,
+}
+
+impl EditedChunks {
+    /// Marks the chunk of every change edited, keeping the voxel it replaced
+    /// on the chunk's border the first time that voxel changes.
+    pub fn record(&mut self, changes: &[VoxelChange]) {
+        for change in changes {
+            self.chunks.insert(change.chunk_pos);
+
+            let pos = change.pos();
+            let border =
+                pos.cmpeq(UVec3::ONE).any() || pos.cmpeq(UVec3::splat(unpad::LEN as u32)).any();
+            if border {
+                self.original
+                    .entry(change.chunk_pos)
+                    .or_default()
+                    .entry(pos)
+                    .or_insert(change.old);
+            }
+        }
+    }
+
+    /// Moves the kept border voxels to the `BlockLibrary` `remap` maps to.
+    pub fn remap(&mut self, remap: &BlockRemap) {
+        for voxel_opt in self.original.values_mut().flat_map(HashMap::values_mut) {
+            *voxel_opt = remap.get(*voxel_opt);
+        }
+    }
+
+    /// Forgets `chunk_pos`, returning its border voxels changed since it was
+    /// loaded as they were before. `None` if it wasn't edited.
+    pub fn take(&mut self, chunk_pos: ChunkPos) -> Option<HashMap<UVec3, Option<VoxelIndex>>> {
+        let original = self.original.remove(&chunk_pos);
+        self.chunks
+            .remove(&chunk_pos)
+            .then(|| original.unwrap_or_default())
+    }
+}
+
+#[derive(SystemParam)]
+pub struct TerrainEditor<'w, 's> {
+    block_library: Res<'w, BlockLibrary>,
+    terrains: Query<
+        'w,
+        's,
+        (
+            &'static ChunkMap,
+            &'static mut DirtyChunks,
+            &'static mut EditedChunks,
+            &'static mut EditHistory,
+            &'static mut LightUpdates,
+        ),
+    >,
+    chunk_changed: MessageWriter<'w, ChunkChanged>,
+}
+
+impl TerrainEditor<'_, '_> {
+    /// Returns `false` if the chunk containing `voxel_pos` isn't loaded.
+    pub fn set(
+        &mut self,
+        terrain: Entity,
+        voxel_pos: IVec3,
+        voxel_opt: Option<VoxelIndex>,
+    ) -> bool {
+        let mut written = false;
+        self.edit(
+            terrain,
+            true,
+            |chunk_map, block_library, changed, changes| {
+                written = chunk_map.set_voxel(
+                    voxel_pos,
+                    voxel_opt,
+                    block_library,
+                    changed,
+                    Some(changes),
+                );
+            },
+        );
+        written
+    }
+
+    /// Fills `min..=max`.
+    pub fn fill_box(
+        &mut self,
+        terrain: Entity,
+        min: IVec3,
+        max: IVec3,
+        voxel_opt: Option<VoxelIndex>,
+    ) -> usize {
+        self.apply(
+            terrain,
+            box_positions(min, max).map(|voxel_pos| (voxel_pos, voxel_opt)),
+        )
+    }
+
+    /// Fills every voxel whose center is within `radius` of `center`'s center.
+    pub fn fill_sphere(
+        &mut self,
+        terrain: Entity,
+        center: IVec3,
+        radius: f32,
+        voxel_opt: Option<VoxelIndex>,
+    ) -> usize {
+        let extent = IVec3::splat(radius.max(0.0).floor() as i32);
+        let radius_sq = radius * radius;
+
+        let voxels = box_positions(center - extent, center + extent)
+            .filter(|voxel_pos| (*voxel_pos - center).as_vec3().length_squared() <= radius_sq)
+            .map(|voxel_pos| (voxel_pos, voxel_opt));
+
+        self.apply(terrain, voxels)
+    }
+
+    /// Replaces `from` with `to` in `min..=max`.
+    pub fn replace(
+        &mut self,
+        terrain: Entity,
+        min: IVec3,
+        max: IVec3,
+        from: Option<VoxelIndex>,
+        to: Option<VoxelIndex>,
+    ) -> usize {
+        let Ok((chunk_map, ..)) = self.terrains.get(terrain) else {
+            return 0;
+        };
+
+        let found = chunk_map.find_voxels(min, max, |voxel_opt| voxel_opt == from);
+
+        self.apply(terrain, found.into_iter().map(|voxel_pos| (voxel_pos, to)))
+    }
+
+    /// Writes every voxel in `voxels`, returning how many were in loaded chunks.
+    pub fn apply(
+        &mut self,
+        terrain: Entity,
+        voxels: impl IntoIterator<Item = (IVec3, Option<VoxelIndex>)>,
+    ) -> usize {
+        self.write(terrain, true, voxels)
+    }
+
+    /// Groups every edit of `terrain` until `commit` into one undo step.
+    pub fn begin(&mut self, terrain: Entity) {
+        if let Ok((.., mut history, _)) = self.terrains.get_mut(terrain) {
+            history.begin();
+        }
+    }
+
+    pub fn commit(&mut self, terrain: Entity) {
+        if let Ok((.., mut history, _)) = self.terrains.get_mut(terrain) {
+            history.commit();
+        }
+    }
+
+    /// Reverts the latest transaction, returns `false` if there is none.
+    ///
+    /// Voxels in chunks that have since been unloaded are skipped.
+    pub fn undo(&mut self, terrain: Entity) -> bool {
+        self.replay(terrain, |history| {
+            history
+                .undo()
+                .map(|transaction| transaction.undo_voxels().collect())
+        })
+    }
+
+    /// Reapplies the latest undone transaction, returns `false` if there is none.
+    pub fn redo(&mut self, terrain: Entity) -> bool {
+        self.replay(terrain, |history| {
+            history
+                .redo()
+                .map(|transaction| transaction.redo_voxels().collect())
+        })
+    }
+
+    fn replay(
+        &mut self,
+        terrain: Entity,
+        f: impl FnOnce(&mut EditHistory) -> Option<Vec<(IVec3, Option<VoxelIndex>)>>,
+    ) -> bool {
+        let Ok((.., mut history, _)) = self.terrains.get_mut(terrain) else {
+            return false;
+        };
+
+        let Some(voxels) = f(&mut history) else {
+            return false;
+        };
+
+        self.write(terrain, false, voxels);
+        true
+    }
+
+    fn write(
+        &mut self,
+        terrain: Entity,
+        record: bool,
+        voxels: impl IntoIterator<Item = (IVec3, Option<VoxelIndex>)>,
+    ) -> usize {
+        let mut count = 0;
+        self.edit(
+            terrain,
+            record,
+            |chunk_map, block_library, changed, changes| {
+                count = chunk_map.set_voxels(voxels, block_library, changed, Some(changes));
+            },
+        );
+        count
+    }
+
+    fn edit(
+        &mut self,
+        terrain: Entity,
+        record: bool,
+        f: impl FnOnce(&ChunkMap, &BlockLibrary, &mut DirtyChunks, &mut Vec<VoxelChange>),
+    ) {
+        let Ok((chunk_map, mut dirty_chunks, mut edited_chunks, mut history, mut light_updates)) =
+            self.terrains.get_mut(terrain)
+        else {
+            warn!("{terrain} is not a terrain");
+            return;
+        };
+
+        let mut changed = DirtyChunks::default();
+        let mut changes = Vec::new();
+        f(chunk_map, &self.block_library, &mut changed, &mut changes);
+
+        light_updates
+            .voxels
+            .extend(changes.iter().map(VoxelChange::voxel_pos));
+
+        dirty_chunks.extend(changed.iter().copied());
+        edited_chunks.extend(changed.iter().copied());
+        edited_chunks.record(&changes);
+
+        if record {
+            history.record(changes);
+        }
+
+        self.chunk_changed
+            .write_batch(changed.iter().map(|chunk_pos| ChunkChanged {
+                terrain,
+                chunk_pos: *chunk_pos,
+            }));
+    }
+}
+
+fn box_positions(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+    (min.z..=max.z).flat_map(move |z| {
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+    })
+}
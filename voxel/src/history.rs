@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::{
+    block_lib::remap::BlockRemap,
+    chunk::{ChunkPos, VoxelIndex, chunk_origin, pad},
+};
+
+// Edits made through `TerrainEditor` are recorded as the voxels they
+// changed in the chunk owning each voxel, padding is rebuilt when the
+// change is replayed. Undo and redo go back through `TerrainEditor` so
+// they update masks, padding and meshes like any other edit.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelChange {
+    pub chunk_pos: ChunkPos,
+    /// `pad::linearize` of the position inside `chunk_pos`.
+    pub index: u32,
+    pub old: Option<VoxelIndex>,
+    pub new: Option<VoxelIndex>,
+}
+
+impl VoxelChange {
+    #[inline]
+    pub fn new(
+        chunk_pos: ChunkPos,
+        pos: UVec3,
+        old: Option<VoxelIndex>,
+        new: Option<VoxelIndex>,
+    ) -> Self {
+        Self {
+            chunk_pos,
+            index: pad::linearize(pos) as u32,
+            old,
+            new,
+        }
+    }
+
+    /// Position inside `chunk_pos`.
+    #[inline]
+    pub fn pos(&self) -> UVec3 {
+        pad::delinearize(self.index as usize)
+    }
+
+    #[inline]
+    pub fn voxel_pos(&self) -> IVec3 {
+        chunk_origin(self.chunk_pos) + self.pos().as_ivec3()
+    }
+}
+
+/// Changes undone or redone together, in the order they were made.
+#[derive(Debug, Clone, Default)]
+pub struct Transaction {
+    pub changes: Vec<VoxelChange>,
+}
+
+impl Transaction {
+    /// Voxels to write to undo this transaction.
+    pub fn undo_voxels(&self) -> impl Iterator<Item = (IVec3, Option<VoxelIndex>)> {
+        self.changes
+            .iter()
+            .rev()
+            .map(|change| (change.voxel_pos(), change.old))
+    }
+
+    /// Voxels to write to redo this transaction.
+    pub fn redo_voxels(&self) -> impl Iterator<Item = (IVec3, Option<VoxelIndex>)> {
+        self.changes
+            .iter()
+            .map(|change| (change.voxel_pos(), change.new))
+    }
+}
+
+#[derive(Component)]
+pub struct EditHistory {
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    open: Option<Transaction>,
+    /// Oldest transactions are dropped past this many.
+    pub limit: usize,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open: None,
+            limit: 256,
+        }
+    }
+}
+
+impl EditHistory {
+    /// Groups every edit until `commit` into one transaction. Does nothing if one is open.
+    pub fn begin(&mut self) {
+        self.open.get_or_insert_default();
+    }
+
+    pub fn commit(&mut self) {
+        if let Some(transaction) = self.open.take() {
+            self.push(transaction);
+        }
+    }
+
+    /// Adds `changes` to the open transaction, or to a new one if none is open.
+    pub fn record(&mut self, changes: Vec<VoxelChange>) {
+        if changes.is_empty() {
+            return;
+        }
+
+        match &mut self.open {
+            Some(transaction) => transaction.changes.extend(changes),
+            None => self.push(Transaction { changes }),
+        }
+    }
+
+    fn push(&mut self, transaction: Transaction) {
+        if transaction.changes.is_empty() {
+            return;
+        }
+
+        self.redo.clear();
+        self.undo.push_back(transaction);
+
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Commits the open transaction and moves the latest one to the redo stack.
+    pub fn undo(&mut self) -> Option<&Transaction> {
+        self.commit();
+
+        let transaction = self.undo.pop_back()?;
+        self.redo.push(transaction);
+        self.redo.last()
+    }
+
+    /// Moves the latest undone transaction back to the undo stack.
+    pub fn redo(&mut self) -> Option<&Transaction> {
+        let transaction = self.redo.pop()?;
+        self.undo.push_back(transaction);
+        self.undo.back()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.open.as_ref().is_some_and(|t| !t.changes.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = None;
+    }
+
+    /// Moves every recorded voxel to the `BlockLibrary` `remap` maps to.
+    pub fn remap(&mut self, remap: &BlockRemap) {
+        let transactions = self
+            .undo
+            .iter_mut()
+            .chain(&mut self.redo)
+            .chain(&mut self.open);
+
+        for change in transactions.flat_map(|transaction| &mut transaction.changes) {
+            change.old = remap.get(change.old);
+            change.new = remap.get(change.new);
+        }
+    }
+}
@@ -1,12 +1,15 @@
-mod block_lib;
-mod chunk;
-mod generator;
-mod math;
+pub mod block_lib;
+pub mod chunk;
+pub mod edit;
+pub mod history;
+pub mod math;
+pub mod physics;
+pub mod raycast;
 mod render;
+pub mod save;
+pub mod streaming;
 mod terrain;
 mod viewer;
-mod voxel;
-mod voxel_volume;
 
 // use std::{ops::Deref, sync::Arc};
 // use bevy::prelude::*;
@@ -5,7 +5,7 @@ pub use Axis::*;
 
 pub type AxisMap<T> = EnumMap<Axis, T>;
 
-#[derive(Debug, Clone, Copy, Enum, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Enum, Deserialize, Serialize)]
 pub enum Axis {
     X,
     Y,
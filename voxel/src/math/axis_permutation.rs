@@ -5,7 +5,7 @@ use super::axis::*;
 
 pub use AxisPermutation::*;
 
-#[derive(Debug, Clone, Copy, Enum, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Enum, Deserialize, Serialize)]
 pub enum AxisPermutation {
     XYZ,
     YZX,
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 pub use Sign::*;
 
 #[repr(i8)]
-#[derive(Debug, Clone, Copy, Enum, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Enum, Deserialize, Serialize)]
 pub enum Sign {
     Pos = 1,
     Neg = -1,
@@ -15,4 +15,12 @@ impl Sign {
     pub const fn as_i8(&self) -> i8 {
         (*self) as i8
     }
+
+    #[inline]
+    pub const fn flip(&self) -> Self {
+        match self {
+            Pos => Neg,
+            Neg => Pos,
+        }
+    }
 }
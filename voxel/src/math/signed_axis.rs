@@ -56,6 +56,18 @@ impl SignedAxis {
         }
     }
 
+    #[inline]
+    pub const fn from_components(sign: Sign, axis: Axis) -> Self {
+        match (sign, axis) {
+            (Pos, X) => PosX,
+            (Neg, X) => NegX,
+            (Pos, Y) => PosY,
+            (Neg, Y) => NegY,
+            (Pos, Z) => PosZ,
+            (Neg, Z) => NegZ,
+        }
+    }
+
     #[inline]
     pub const fn components(&self) -> (Sign, Axis) {
         match self {
@@ -0,0 +1,255 @@
+use bevy::{
+    math::{Vec3A, bounding::Aabb3d},
+    prelude::*,
+};
+
+use crate::{
+    block_lib::BlockLibrary,
+    chunk::{ChunkMap, VoxelIndex},
+    terrain::Terrain,
+};
+
+// Queries run in the local space of a terrain, where the voxel at `v`
+// spans `v..v + 1` and its `Block::collision_aabbs` are offset by `v`.
+// Unloaded chunks have no colliders.
+
+/// Gap kept between a swept body and what it hit so it doesn't start
+/// the next sweep overlapping.
+const SKIN: f32 = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepHit {
+    /// Fraction of the motion travelled before impact, in `0.0..=1.0`.
+    pub time: f32,
+    /// Unit normal of the face that was hit.
+    pub normal: Vec3,
+    pub voxel_pos: IVec3,
+    pub voxel: VoxelIndex,
+}
+
+impl ChunkMap {
+    /// Calls `f` with every collider overlapping `aabb`.
+    pub fn for_each_collider(
+        &self,
+        block_library: &BlockLibrary,
+        aabb: Aabb3d,
+        mut f: impl FnMut(IVec3, VoxelIndex, Aabb3d),
+    ) {
+        // colliders may stick out of their voxel by up to one voxel
+        let min = Vec3::from(aabb.min).floor().as_ivec3() - IVec3::ONE;
+        let max = Vec3::from(aabb.max).floor().as_ivec3() + IVec3::ONE;
+
+        self.for_each_voxel(min, max, |voxel_pos, voxel_opt| {
+            let Some(voxel) = voxel_opt else {
+                return;
+            };
+
+            let offset = Vec3A::from(voxel_pos.as_vec3());
+            for collider in &block_library[voxel].collision_aabbs {
+                let collider = Aabb3d {
+                    min: collider.min + offset,
+                    max: collider.max + offset,
+                };
+
+                if aabbs_overlap(aabb, collider, 0.0) {
+                    f(voxel_pos, voxel, collider);
+                }
+            }
+        });
+    }
+
+    /// Whether `aabb` overlaps any collider, touching doesn't count.
+    pub fn overlaps(&self, block_library: &BlockLibrary, aabb: Aabb3d) -> bool {
+        let mut overlapping = false;
+        self.for_each_collider(block_library, aabb, |_, _, collider| {
+            overlapping |= aabbs_overlap(aabb, collider, SKIN * 0.5);
+        });
+        overlapping
+    }
+
+    /// First collider hit moving `aabb` by `motion`.
+    ///
+    /// Colliders `aabb` already overlaps are ignored so a body can move out of them.
+    pub fn sweep(
+        &self,
+        block_library: &BlockLibrary,
+        aabb: Aabb3d,
+        motion: Vec3,
+    ) -> Option<SweepHit> {
+        let motion = Vec3A::from(motion);
+        let swept = Aabb3d {
+            min: aabb.min.min(aabb.min + motion),
+            max: aabb.max.max(aabb.max + motion),
+        };
+
+        let mut nearest: Option<SweepHit> = None;
+
+        self.for_each_collider(block_library, swept, |voxel_pos, voxel, collider| {
+            let Some((time, normal)) = sweep_aabb(aabb, motion, collider) else {
+                return;
+            };
+
+            if nearest.is_none_or(|hit| time < hit.time) {
+                nearest = Some(SweepHit {
+                    time,
+                    normal,
+                    voxel_pos,
+                    voxel,
+                });
+            }
+        });
+
+        nearest
+    }
+
+    /// Moves `aabb` by `motion`, sliding along whatever it hits.
+    ///
+    /// Returns the distance moved and the normals of every hit.
+    pub fn move_and_slide(
+        &self,
+        block_library: &BlockLibrary,
+        mut aabb: Aabb3d,
+        mut motion: Vec3,
+        max_slides: usize,
+    ) -> (Vec3, Vec<Vec3>) {
+        let start = aabb.min;
+        let mut normals = Vec::new();
+
+        for _ in 0..=max_slides {
+            if motion.length_squared() <= f32::EPSILON {
+                break;
+            }
+
+            let Some(hit) = self.sweep(block_library, aabb, motion) else {
+                translate(&mut aabb, motion);
+                break;
+            };
+
+            translate(&mut aabb, motion * hit.time + hit.normal * SKIN);
+
+            // drop the part of the remaining motion going into the surface
+            let remaining = motion * (1.0 - hit.time);
+            motion = remaining - hit.normal * remaining.dot(hit.normal);
+
+            normals.push(hit.normal);
+        }
+
+        (Vec3::from(aabb.min - start), normals)
+    }
+}
+
+#[inline]
+fn translate(aabb: &mut Aabb3d, translation: Vec3) {
+    let translation = Vec3A::from(translation);
+    aabb.min += translation;
+    aabb.max += translation;
+}
+
+#[inline]
+fn aabbs_overlap(a: Aabb3d, b: Aabb3d, tolerance: f32) -> bool {
+    (a.min + tolerance).cmplt(b.max).all() && (a.max - tolerance).cmpgt(b.min).all()
+}
+
+/// Time of impact in `0.0..=1.0` and normal of `moving` moved by `motion` against `fixed`.
+fn sweep_aabb(moving: Aabb3d, motion: Vec3A, fixed: Aabb3d) -> Option<(f32, Vec3)> {
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut entry_axis = 0;
+
+    for axis in 0..3 {
+        let d = motion[axis];
+
+        let (axis_entry, axis_exit) = if d > 0.0 {
+            (
+                (fixed.min[axis] - moving.max[axis]) / d,
+                (fixed.max[axis] - moving.min[axis]) / d,
+            )
+        } else if d < 0.0 {
+            (
+                (fixed.max[axis] - moving.min[axis]) / d,
+                (fixed.min[axis] - moving.max[axis]) / d,
+            )
+        } else if moving.max[axis] > fixed.min[axis] && moving.min[axis] < fixed.max[axis] {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            return None;
+        };
+
+        if axis_entry > entry {
+            entry = axis_entry;
+            entry_axis = axis;
+        }
+        exit = exit.min(axis_exit);
+    }
+
+    // already overlapping, missed or out of reach
+    if entry > exit || !(0.0..=1.0).contains(&entry) {
+        return None;
+    }
+
+    let mut normal = Vec3::ZERO;
+    normal[entry_axis] = -motion[entry_axis].signum();
+
+    Some((entry, normal))
+}
+
+/// Moves its entity through the voxels of `terrain` by `velocity` every frame.
+///
+/// The entity's `Transform` must be in the local space of the terrain.
+#[derive(Component, Debug, Clone)]
+#[require(Transform)]
+pub struct KinematicController {
+    pub terrain: Entity,
+    /// Collider centered on the entity's translation.
+    pub half_size: Vec3,
+    pub velocity: Vec3,
+    pub max_slides: usize,
+    /// Whether the last move hit a surface facing up.
+    pub grounded: bool,
+}
+
+impl KinematicController {
+    pub fn new(terrain: Entity, half_size: Vec3) -> Self {
+        Self {
+            terrain,
+            half_size,
+            velocity: Vec3::ZERO,
+            max_slides: 3,
+            grounded: false,
+        }
+    }
+}
+
+pub fn move_kinematic_controllers(
+    time: Res<Time>,
+    block_library: Res<BlockLibrary>,
+    terrains: Query<&ChunkMap, With<Terrain>>,
+    controllers: Query<(&mut Transform, &mut KinematicController)>,
+) {
+    let delta = time.delta_secs();
+
+    for (mut transform, mut controller) in controllers {
+        let Ok(chunk_map) = terrains.get(controller.terrain) else {
+            continue;
+        };
+
+        let aabb = Aabb3d::new(transform.translation, controller.half_size);
+        let motion = controller.velocity * delta;
+
+        let (moved, normals) =
+            chunk_map.move_and_slide(&block_library, aabb, motion, controller.max_slides);
+
+        transform.translation += moved;
+
+        controller.grounded = false;
+        for normal in normals {
+            // stop moving into what was hit
+            let into = controller.velocity.dot(normal);
+            if into < 0.0 {
+                controller.velocity -= normal * into;
+            }
+
+            controller.grounded |= normal.y > 0.5;
+        }
+    }
+}
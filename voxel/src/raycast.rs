@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::{
+    block_lib::BlockLibrary,
+    chunk::{ChunkMap, ChunkPos, VoxelIndex, voxel_chunk_pos, voxel_local_pos},
+    math::signed_axis::*,
+};
+
+// Amanatides & Woo, "A Fast Voxel Traversal Algorithm for Ray Tracing".
+//
+// The voxel at `v` spans `v..v + 1` in the local space of its terrain.
+// The ray stops at the first unloaded chunk, so it can't see through
+// terrain that isn't there yet and always ends, even without a
+// `max_distance`.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub voxel_pos: IVec3,
+    pub voxel: VoxelIndex,
+    /// Face of the voxel the ray entered through.
+    pub face: SignedAxis,
+    /// Distance along the normalized direction.
+    pub distance: f32,
+}
+
+impl RayHit {
+    /// Position of the empty voxel in front of `face`, e.g. where to place a block.
+    #[inline]
+    pub fn adjacent_pos(&self) -> IVec3 {
+        self.voxel_pos + IVec3::from_array(self.face.coords())
+    }
+}
+
+/// Filter for `ChunkMap::raycast` that skips transparent blocks.
+pub fn opaque(block_library: &BlockLibrary) -> impl FnMut(VoxelIndex) -> bool {
+    move |voxel| !block_library[voxel].is_transparent
+}
+
+impl ChunkMap {
+    /// First voxel within `max_distance` of `origin` for which `filter` returns `true`.
+    ///
+    /// `max_distance` may be `f32::INFINITY`, a NaN `max_distance` or a
+    /// non-finite `origin` never hits.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        mut filter: impl FnMut(VoxelIndex) -> bool,
+    ) -> Option<RayHit> {
+        let direction = direction.try_normalize()?;
+
+        if max_distance.is_nan() || !origin.is_finite() {
+            return None;
+        }
+
+        let mut voxel_pos = origin.floor().as_ivec3();
+        let step = direction.signum().as_ivec3();
+
+        // distance along the ray to cross one voxel on each axis
+        let delta = direction.recip().abs();
+
+        // distance along the ray to the first border on each axis
+        let next_border = voxel_pos.as_vec3() + step.max(IVec3::ZERO).as_vec3();
+        let mut t_max = Vec3::select(
+            direction.cmpeq(Vec3::ZERO),
+            Vec3::INFINITY,
+            (next_border - origin) / direction,
+        );
+
+        let entered = |axis: usize| match (axis, step[axis] > 0) {
+            (0, true) => NegX,
+            (0, false) => PosX,
+            (1, true) => NegY,
+            (1, false) => PosY,
+            (_, true) => NegZ,
+            (_, false) => PosZ,
+        };
+
+        // a ray starting inside a voxel enters it against its major axis
+        let mut face = entered(direction.abs().max_position());
+        let mut distance = 0.0;
+
+        let mut cached: Option<(ChunkPos, _)> = None;
+
+        while distance <= max_distance {
+            let chunk_pos = voxel_chunk_pos(voxel_pos);
+            if cached
+                .as_ref()
+                .is_none_or(|(cached_pos, _)| *cached_pos != chunk_pos)
+            {
+                // release the previous chunk before locking the next
+                drop(cached.take());
+                cached = Some((chunk_pos, self.get(&chunk_pos)?));
+            }
+
+            let voxel_opt = cached
+                .as_ref()
+                .and_then(|(_, chunk)| chunk.get(voxel_local_pos(voxel_pos)));
+
+            if let Some(voxel) = voxel_opt {
+                // `filter` may lock chunks itself
+                cached = None;
+
+                if filter(voxel) {
+                    return Some(RayHit {
+                        voxel_pos,
+                        voxel,
+                        face,
+                        distance,
+                    });
+                }
+            }
+
+            let axis = t_max.min_position();
+
+            distance = t_max[axis];
+            voxel_pos[axis] += step[axis];
+            t_max[axis] += delta[axis];
+            face = entered(axis);
+        }
+
+        None
+    }
+}
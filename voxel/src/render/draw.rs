@@ -13,7 +13,7 @@ use bevy::{
 use bytemuck::{Pod, Zeroable};
 
 use crate::{
-    chunk::VoxelQuad,
+    chunk::{ModelQuad, VoxelQuad},
     render::{alloc_buffer::AllocBufferPlugin, BaseQuadBuffer, IndirectTerrainBuffers},
 };
 
@@ -23,7 +23,13 @@ struct CustomMaterialPlugin;
 
 impl Plugin for CustomMaterialPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(AllocBufferPlugin::<VoxelQuad>::default())
+        app
+            .add_plugins((
+                AllocBufferPlugin::<VoxelQuad>::default(),
+                // stored by the `ModelMesher` but not drawn yet, pending
+                // along with the pipeline set up on `RenderStartup`
+                AllocBufferPlugin::<ModelQuad>::default(),
+            ))
             .sub_app_mut(RenderApp)
             .add_render_command()
             .add_systems(RenderStartup, todo!()) //init custom pipeline
@@ -84,6 +90,12 @@ mod pipeline {
                         offset: VertexFormat::Sint32x3.size(),
                         shader_location: 4,
                     },
+                    // lighting: { ao: 4 x u2, light: u8, lod: u2 }
+                    VertexAttribute {
+                        format: VertexFormat::Uint32,
+                        offset: VertexFormat::Sint32x3.size() + VertexFormat::Uint32.size(),
+                        shader_location: 5,
+                    },
                 ]
             });
 
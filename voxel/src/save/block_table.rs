@@ -0,0 +1,94 @@
+use anyhow::{Result, bail};
+use bevy::platform::collections::HashMap;
+use serde_json::de::from_slice as json_de;
+use std::{fs, path::Path};
+
+use crate::{block_lib::BlockLibrary, chunk::VoxelIndex};
+
+// Saved chunks refer to blocks by their index in this table instead
+// of by `VoxelIndex`, which depends on load order. Indices are never
+// reassigned, so adding or removing blocks from the `BlockLibrary`
+// leaves saved data valid; unknown names load as `None`.
+
+/// Reserved for `None`.
+pub const NONE: u16 = u16::MAX;
+
+#[derive(Debug, Default)]
+pub struct BlockTable {
+    names: Vec<String>,
+    indices: HashMap<String, u16>,
+    dirty: bool,
+}
+
+impl BlockTable {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let names: Vec<String> = json_de(&fs::read(path)?)?;
+
+        let indices = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), index as u16))
+            .collect();
+
+        Ok(Self {
+            names,
+            indices,
+            dirty: false,
+        })
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(&self.names)?)?;
+        self.dirty = false;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn get_or_insert(&mut self, name: &str) -> Result<u16> {
+        if let Some(index) = self.indices.get(name) {
+            return Ok(*index);
+        }
+
+        let index = self.names.len();
+        if index >= NONE as usize {
+            bail!("Block table is full, cannot insert {name}");
+        }
+
+        let index = index as u16;
+        self.names.push(name.to_owned());
+        self.indices.insert(name.to_owned(), index);
+        self.dirty = true;
+
+        Ok(index)
+    }
+
+    #[inline]
+    pub fn name(&self, index: u16) -> Option<&str> {
+        self.names.get(index as usize).map(String::as_str)
+    }
+
+    pub fn to_persisted(
+        &mut self,
+        voxel_opt: Option<VoxelIndex>,
+        block_library: &BlockLibrary,
+    ) -> Result<u16> {
+        match voxel_opt {
+            Some(voxel) => self.get_or_insert(&block_library.identifier_string(voxel)),
+            None => Ok(NONE),
+        }
+    }
+
+    /// `None` if `index` is `NONE` or names a block missing from `block_library`.
+    pub fn to_runtime(&self, index: u16, block_library: &BlockLibrary) -> Option<VoxelIndex> {
+        block_library.lookup(self.name(index)?)
+    }
+}
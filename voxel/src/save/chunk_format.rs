@@ -0,0 +1,192 @@
+use anyhow::{Result, bail, ensure};
+use bevy::prelude::*;
+use std::io::{Read, Write};
+
+use crate::{
+    block_lib::BlockLibrary,
+    chunk::{Chunk, PalettedVoxels, VoxelIndex, Voxels, pad::VOL},
+};
+
+use super::block_table::{self, BlockTable};
+
+// Little endian.
+//
+// magic: [u8; 4]
+// version: u16
+// kind: u8
+//
+// UNIFORM:
+//   entry
+//
+// PALETTED:
+//   palette_len: u32
+//   palette: [entry; palette_len]
+//   bits: u8
+//   words: [u64; VOL * bits / 64]
+//
+// entry (version 2):
+//   index: u16 (into the world `BlockTable`, `block_table::NONE` for `None`)
+//
+// entry (version 1, read only):
+//   len: u16
+//   identifier: [u8; len] (`namespace:name`, empty for `None`)
+
+const MAGIC: [u8; 4] = *b"VXCK";
+pub const VERSION: u16 = 2;
+
+const NAMED_VERSION: u16 = 1;
+
+const UNIFORM: u8 = 0;
+const PALETTED: u8 = 1;
+
+pub fn encode(
+    chunk: &Chunk,
+    block_library: &BlockLibrary,
+    block_table: &mut BlockTable,
+    writer: &mut impl Write,
+) -> Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+
+    match chunk.voxels() {
+        Voxels::Uniform(voxel_opt) => {
+            writer.write_all(&[UNIFORM])?;
+            write_entry(writer, *voxel_opt, block_library, block_table)?;
+        }
+        Voxels::Paletted(paletted) => {
+            writer.write_all(&[PALETTED])?;
+
+            let palette = paletted.palette();
+            writer.write_all(&(palette.len() as u32).to_le_bytes())?;
+            for voxel_opt in palette {
+                write_entry(writer, *voxel_opt, block_library, block_table)?;
+            }
+
+            writer.write_all(&[paletted.bits() as u8])?;
+            for word in paletted.words() {
+                writer.write_all(&word.to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn decode<R: Read>(
+    reader: &mut R,
+    block_library: &BlockLibrary,
+    block_table: &BlockTable,
+) -> Result<Chunk> {
+    let magic: [u8; 4] = read_array(reader)?;
+    ensure!(magic == MAGIC, "Invalid chunk magic {magic:?}");
+
+    let version = u16::from_le_bytes(read_array(reader)?);
+    ensure!(
+        version == VERSION || version == NAMED_VERSION,
+        "Unsupported chunk version {version}"
+    );
+
+    let entry = |reader: &mut R| {
+        if version == NAMED_VERSION {
+            read_named_entry(reader, block_library)
+        } else {
+            read_entry(reader, block_library, block_table)
+        }
+    };
+
+    let [kind] = read_array(reader)?;
+
+    let voxels = match kind {
+        UNIFORM => Voxels::Uniform(entry(reader)?),
+        PALETTED => {
+            let palette_len = u32::from_le_bytes(read_array(reader)?);
+            let palette = (0..palette_len)
+                .map(|_| entry(reader))
+                .collect::<Result<Vec<_>>>()?;
+
+            let [bits] = read_array(reader)?;
+            let bits = bits as u32;
+            ensure!(
+                bits.is_power_of_two() && bits <= 16,
+                "Invalid index width {bits}"
+            );
+
+            let word_count = VOL * bits as usize / 64;
+            let words = (0..word_count)
+                .map(|_| Ok(u64::from_le_bytes(read_array(reader)?)))
+                .collect::<Result<Box<[u64]>>>()?;
+
+            let Some(paletted) = PalettedVoxels::from_parts(palette, bits, words) else {
+                bail!("Inconsistent paletted voxels");
+            };
+
+            Voxels::Paletted(paletted)
+        }
+        _ => bail!("Unknown chunk kind {kind}"),
+    };
+
+    Ok(Chunk::from_voxels(voxels, block_library))
+}
+
+fn write_entry(
+    writer: &mut impl Write,
+    voxel_opt: Option<VoxelIndex>,
+    block_library: &BlockLibrary,
+    block_table: &mut BlockTable,
+) -> Result<()> {
+    let index = block_table.to_persisted(voxel_opt, block_library)?;
+    writer.write_all(&index.to_le_bytes())?;
+
+    Ok(())
+}
+
+fn read_entry(
+    reader: &mut impl Read,
+    block_library: &BlockLibrary,
+    block_table: &BlockTable,
+) -> Result<Option<VoxelIndex>> {
+    let index = u16::from_le_bytes(read_array(reader)?);
+    if index == block_table::NONE {
+        return Ok(None);
+    }
+
+    let voxel_opt = block_table.to_runtime(index, block_library);
+
+    if voxel_opt.is_none() {
+        let name = block_table
+            .name(index)
+            .unwrap_or("<missing from block table>");
+        warn!("Unknown block {name} in saved chunk, replacing with `None`");
+    }
+
+    Ok(voxel_opt)
+}
+
+fn read_named_entry(
+    reader: &mut impl Read,
+    block_library: &BlockLibrary,
+) -> Result<Option<VoxelIndex>> {
+    let len = u16::from_le_bytes(read_array(reader)?) as usize;
+    if len == 0 {
+        return Ok(None);
+    }
+
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    let string = String::from_utf8(bytes)?;
+
+    let voxel_opt = block_library.lookup(&string);
+
+    if voxel_opt.is_none() {
+        warn!("Unknown block {string} in saved chunk, replacing with `None`");
+    }
+
+    Ok(voxel_opt)
+}
+
+#[inline]
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
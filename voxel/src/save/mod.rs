@@ -0,0 +1,168 @@
+pub mod block_table;
+pub mod chunk_format;
+pub mod region;
+
+use anyhow::Result;
+use bevy::{
+    platform::collections::{HashMap, hash_map::Entry},
+    prelude::*,
+};
+use parking_lot::Mutex;
+use std::{fs, path::PathBuf, sync::Arc};
+
+use crate::{
+    block_lib::BlockLibrary,
+    chunk::{Chunk, ChunkMap, ChunkPos},
+};
+
+use block_table::BlockTable;
+use region::{RegionFile, RegionPos, local_index, region_pos};
+
+const BLOCK_TABLE_FILE: &str = "blocks.json";
+
+/// On a terrain, edited chunks are saved here when they unload, see
+/// `ChunkMap::unload`. Load them back with a `StoredGenerator`.
+#[derive(Resource, Component, Clone, Deref)]
+pub struct ChunkStorage(pub Arc<InnerChunkStorage>);
+
+impl ChunkStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        Ok(Self(Arc::new(InnerChunkStorage::new(root)?)))
+    }
+}
+
+pub struct InnerChunkStorage {
+    root: PathBuf,
+    block_table: Mutex<BlockTable>,
+    regions: Mutex<HashMap<RegionPos, RegionFile>>,
+}
+
+impl InnerChunkStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+
+        let block_table = BlockTable::load(&root.join(BLOCK_TABLE_FILE))?;
+
+        Ok(Self {
+            root,
+            block_table: Mutex::new(block_table),
+            regions: default(),
+        })
+    }
+
+    pub fn load_chunk(
+        &self,
+        chunk_pos: ChunkPos,
+        block_library: &BlockLibrary,
+    ) -> Result<Option<Chunk>> {
+        let bytes_opt = self.with_region(chunk_pos, false, |region| {
+            region.read(local_index(chunk_pos))
+        })?;
+
+        let Some(bytes) = bytes_opt.flatten() else {
+            return Ok(None);
+        };
+
+        let block_table = self.block_table.lock();
+        let chunk = chunk_format::decode(&mut bytes.as_slice(), block_library, &block_table)?;
+
+        Ok(Some(chunk))
+    }
+
+    pub fn save_chunk(
+        &self,
+        chunk_pos: ChunkPos,
+        chunk: &Chunk,
+        block_library: &BlockLibrary,
+    ) -> Result<()> {
+        let mut bytes = Vec::new();
+        {
+            let mut block_table = self.block_table.lock();
+            chunk_format::encode(chunk, block_library, &mut block_table, &mut bytes)?;
+
+            // new names must be on disk before any chunk referring to them
+            if block_table.is_dirty() {
+                block_table.save(&self.root.join(BLOCK_TABLE_FILE))?;
+            }
+        }
+
+        self.with_region(chunk_pos, true, |region| {
+            region.write(local_index(chunk_pos), &bytes)
+        })?;
+
+        Ok(())
+    }
+
+    pub fn remove_chunk(&self, chunk_pos: ChunkPos) -> Result<()> {
+        self.with_region(chunk_pos, false, |region| {
+            region.remove(local_index(chunk_pos))
+        })?;
+
+        Ok(())
+    }
+
+    pub fn contains_chunk(&self, chunk_pos: ChunkPos) -> Result<bool> {
+        let contains_opt = self.with_region(chunk_pos, false, |region| {
+            Ok(region.contains(local_index(chunk_pos)))
+        })?;
+
+        Ok(contains_opt.unwrap_or(false))
+    }
+
+    pub fn save_chunk_map(&self, chunk_map: &ChunkMap, block_library: &BlockLibrary) -> Result<()> {
+        for entry in chunk_map.iter() {
+            let (chunk_pos, chunk) = entry.pair();
+            self.save_chunk(*chunk_pos, chunk, block_library)?;
+        }
+
+        self.sync()
+    }
+
+    pub fn sync(&self) -> Result<()> {
+        for region in self.regions.lock().values() {
+            region.sync()?;
+        }
+
+        Ok(())
+    }
+
+    /// Closes every open region file.
+    pub fn close(&self) -> Result<()> {
+        self.sync()?;
+        self.regions.lock().clear();
+
+        Ok(())
+    }
+
+    fn region_path(&self, region_pos: RegionPos) -> PathBuf {
+        let RegionPos { x, y, z } = region_pos;
+        self.root.join(format!("r.{x}.{y}.{z}.bin"))
+    }
+
+    /// Returns `None` if the region does not exist and `create` is `false`.
+    fn with_region<T>(
+        &self,
+        chunk_pos: ChunkPos,
+        create: bool,
+        f: impl FnOnce(&mut RegionFile) -> Result<T>,
+    ) -> Result<Option<T>> {
+        let region_pos = region_pos(chunk_pos);
+
+        let mut regions = self.regions.lock();
+
+        let region = match regions.entry(region_pos) {
+            Entry::Occupied(occupied) => occupied.into_mut(),
+            Entry::Vacant(vacant) => {
+                let path = self.region_path(region_pos);
+                if !create && !path.exists() {
+                    return Ok(None);
+                }
+
+                vacant.insert(RegionFile::open(&path)?)
+            }
+        };
+
+        f(region).map(Some)
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::{Result, ensure};
+use bevy::math::IVec3;
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::chunk::ChunkPos;
+
+// A region groups `LEN`³ chunks into one file.
+//
+// The file is split into `SECTOR` byte sectors. The header occupies
+// the first `HEADER_SECTORS` and holds an offset table with one
+// `Entry` per chunk, indexed by `local_index`. Chunk data is stored
+// in consecutive sectors starting at `Entry::sector`.
+//
+// header:
+//   magic: [u8; 4]
+//   version: u16
+//   entries: [(sector: u32, len: u32); VOL]
+
+pub const BITS: u32 = 4;
+
+pub const LEN: i32 = 1 << BITS;
+pub const VOL: usize = (LEN as usize).pow(3);
+
+const SECTOR: u64 = 4096;
+
+const MAGIC: [u8; 4] = *b"VXRG";
+const VERSION: u16 = 1;
+
+const ENTRIES_OFFSET: u64 = 6;
+const ENTRY_SIZE: u64 = 8;
+const HEADER_SECTORS: u32 = (ENTRIES_OFFSET + VOL as u64 * ENTRY_SIZE).div_ceil(SECTOR) as u32;
+
+pub type RegionPos = IVec3;
+
+#[inline]
+pub fn region_pos(chunk_pos: ChunkPos) -> RegionPos {
+    chunk_pos >> BITS as i32
+}
+
+#[inline]
+pub fn local_index(chunk_pos: ChunkPos) -> usize {
+    let local = (chunk_pos & (LEN - 1)).as_uvec3();
+    (local.x | local.y << BITS | local.z << (2 * BITS)) as usize
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    sector: u32,
+    len: u32,
+}
+
+impl Entry {
+    #[inline]
+    fn sectors(&self) -> u32 {
+        (self.len as u64).div_ceil(SECTOR) as u32
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+pub struct RegionFile {
+    file: File,
+    entries: Box<[Entry]>,
+    used_sectors: Vec<bool>,
+}
+
+impl RegionFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut entries = vec![Entry::default(); VOL].into_boxed_slice();
+
+        if file.metadata()?.len() == 0 {
+            file.write_all(&MAGIC)?;
+            file.write_all(&VERSION.to_le_bytes())?;
+            file.set_len(HEADER_SECTORS as u64 * SECTOR)?;
+        } else {
+            let mut header = vec![0; (ENTRIES_OFFSET + VOL as u64 * ENTRY_SIZE) as usize];
+            file.read_exact(&mut header)?;
+
+            ensure!(header[0..4] == MAGIC, "Invalid region magic in {path:?}");
+
+            let version = u16::from_le_bytes([header[4], header[5]]);
+            ensure!(
+                version == VERSION,
+                "Unsupported region version {version} in {path:?}"
+            );
+
+            let table = &header[ENTRIES_OFFSET as usize..];
+            for (entry, bytes) in entries
+                .iter_mut()
+                .zip(table.chunks_exact(ENTRY_SIZE as usize))
+            {
+                *entry = Entry {
+                    sector: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+                    len: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+                };
+            }
+        }
+
+        let mut used_sectors = vec![true; HEADER_SECTORS as usize];
+        for entry in entries.iter().filter(|e| !e.is_empty()) {
+            let end = (entry.sector + entry.sectors()) as usize;
+            if used_sectors.len() < end {
+                used_sectors.resize(end, false);
+            }
+            used_sectors[entry.sector as usize..end].fill(true);
+        }
+
+        Ok(Self {
+            file,
+            entries,
+            used_sectors,
+        })
+    }
+
+    pub fn read(&mut self, local_index: usize) -> Result<Option<Vec<u8>>> {
+        let entry = self.entries[local_index];
+        if entry.is_empty() {
+            return Ok(None);
+        }
+
+        let mut bytes = vec![0; entry.len as usize];
+        self.file
+            .seek(SeekFrom::Start(entry.sector as u64 * SECTOR))?;
+        self.file.read_exact(&mut bytes)?;
+
+        Ok(Some(bytes))
+    }
+
+    pub fn write(&mut self, local_index: usize, bytes: &[u8]) -> Result<()> {
+        ensure!(!bytes.is_empty(), "Cannot store an empty chunk entry");
+
+        let old = self.entries[local_index];
+
+        let len = u32::try_from(bytes.len())?;
+        let sector = self.allocate(len.div_ceil(SECTOR as u32));
+
+        // data is written before the header so a failed write
+        // leaves the previous entry intact
+        self.file.seek(SeekFrom::Start(sector as u64 * SECTOR))?;
+        self.file.write_all(bytes)?;
+
+        let entry = Entry { sector, len };
+        self.write_entry(local_index, entry)?;
+
+        self.free(old);
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, local_index: usize) -> Result<()> {
+        let old = self.entries[local_index];
+        if old.is_empty() {
+            return Ok(());
+        }
+
+        self.write_entry(local_index, Entry::default())?;
+        self.free(old);
+
+        Ok(())
+    }
+
+    pub fn contains(&self, local_index: usize) -> bool {
+        !self.entries[local_index].is_empty()
+    }
+
+    pub fn sync(&self) -> Result<()> {
+        Ok(self.file.sync_data()?)
+    }
+
+    fn write_entry(&mut self, local_index: usize, entry: Entry) -> Result<()> {
+        let mut bytes = [0; ENTRY_SIZE as usize];
+        bytes[0..4].copy_from_slice(&entry.sector.to_le_bytes());
+        bytes[4..8].copy_from_slice(&entry.len.to_le_bytes());
+
+        let offset = ENTRIES_OFFSET + local_index as u64 * ENTRY_SIZE;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(&bytes)?;
+
+        self.entries[local_index] = entry;
+
+        Ok(())
+    }
+
+    /// First fit, appending to the end of the file when no gap is large enough.
+    fn allocate(&mut self, sectors: u32) -> u32 {
+        let sectors = sectors as usize;
+
+        let mut run_start = 0;
+        let mut run_len = 0;
+        for (index, used) in self.used_sectors.iter().enumerate() {
+            if *used {
+                run_len = 0;
+                continue;
+            }
+
+            if run_len == 0 {
+                run_start = index;
+            }
+            run_len += 1;
+
+            if run_len == sectors {
+                break;
+            }
+        }
+
+        if run_len != sectors {
+            run_start = self.used_sectors.len() - run_len;
+        }
+
+        let end = run_start + sectors;
+        if self.used_sectors.len() < end {
+            self.used_sectors.resize(end, false);
+        }
+        self.used_sectors[run_start..end].fill(true);
+
+        run_start as u32
+    }
+
+    fn free(&mut self, entry: Entry) {
+        if entry.is_empty() {
+            return;
+        }
+
+        let start = entry.sector as usize;
+        let end = start + entry.sectors() as usize;
+        self.used_sectors[start..end].fill(false);
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::{platform::collections::HashSet, prelude::*};
+use std::cmp::Reverse;
+
+use crate::{
+    block_lib::BlockLibrary,
+    chunk::{
+        ChunkMap, ChunkMeshMap, ChunkPos, DirtyChunks, GenerationTasks, LightMap, LightUpdates,
+        MeshingTasks, ModelQuad, SaveTasks, VoxelQuad,
+        generator::{PendingWrites, TerrainGenerator},
+        point_chunk_pos,
+    },
+    edit::EditedChunks,
+    render::alloc_buffer::AllocBuffer,
+    save::ChunkStorage,
+    viewer::Viewer,
+};
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct StreamingSettings {
+    /// Generation tasks spawned per frame per terrain.
+    pub load_budget: usize,
+    /// Generation tasks in flight per terrain.
+    pub max_generating: usize,
+    /// Chunks unloaded per frame per terrain.
+    pub unload_budget: usize,
+    /// Chunks are kept until they are this many chunks past `Viewer::radius`
+    /// so moving back and forth across a chunk border doesn't thrash.
+    pub hysteresis: i32,
+}
+
+impl Default for StreamingSettings {
+    fn default() -> Self {
+        Self {
+            load_budget: 16,
+            max_generating: 64,
+            unload_budget: 64,
+            hysteresis: 2,
+        }
+    }
+}
+
+/// Per terrain streaming queues.
+///
+/// Queues are only rebuilt when a viewer crosses a chunk border or changes
+/// radius and are drained by the per-frame budgets in between.
+#[derive(Component, Default)]
+pub struct ChunkStreaming {
+    origins: Vec<(ChunkPos, i32)>,
+    /// Sorted farthest first so `pop` returns the nearest chunk.
+    load_queue: Vec<ChunkPos>,
+    /// Sorted nearest first so `pop` returns the farthest chunk.
+    unload_queue: Vec<ChunkPos>,
+}
+
+impl ChunkStreaming {
+    fn rebuild(
+        &mut self,
+        origins: Vec<(ChunkPos, i32)>,
+        chunk_map: &ChunkMap,
+        settings: &StreamingSettings,
+    ) {
+        let nearest_distance_sq = |chunk_pos: ChunkPos| {
+            origins
+                .iter()
+                .map(|(origin, _)| (chunk_pos - *origin).length_squared())
+                .min()
+                .unwrap_or(i32::MAX)
+        };
+
+        let visible = origins
+            .iter()
+            .flat_map(|(origin, radius)| Viewer::new(*radius).visible_positions(*origin))
+            .collect::<HashSet<_>>();
+
+        self.load_queue.clear();
+        self.load_queue.extend(
+            visible
+                .into_iter()
+                .filter(|chunk_pos| !chunk_map.contains_key(chunk_pos)),
+        );
+        self.load_queue
+            .sort_by_cached_key(|chunk_pos| Reverse(nearest_distance_sq(*chunk_pos)));
+
+        self.unload_queue.clear();
+        self.unload_queue.extend(
+            chunk_map
+                .iter()
+                .map(|entry| *entry.key())
+                .filter(|chunk_pos| !in_range(&origins, *chunk_pos, settings.hysteresis)),
+        );
+        self.unload_queue
+            .sort_by_cached_key(|chunk_pos| nearest_distance_sq(*chunk_pos));
+
+        self.origins = origins;
+    }
+
+    /// Next chunk to load, nearest to any viewer first.
+    ///
+    /// Chunks still being saved are skipped, they are queued again once the
+    /// queues are rebuilt.
+    pub fn next_load(
+        &mut self,
+        chunk_map: &ChunkMap,
+        generation_tasks: &GenerationTasks,
+        save_tasks: &SaveTasks,
+    ) -> Option<ChunkPos> {
+        while let Some(chunk_pos) = self.load_queue.pop() {
+            if !chunk_map.contains_key(&chunk_pos)
+                && !generation_tasks.contains(chunk_pos)
+                && !save_tasks.contains(chunk_pos)
+            {
+                return Some(chunk_pos);
+            }
+        }
+        None
+    }
+
+    /// Next chunk to unload, farthest from every viewer first.
+    pub fn next_unload(&mut self, chunk_map: &ChunkMap, hysteresis: i32) -> Option<ChunkPos> {
+        while let Some(chunk_pos) = self.unload_queue.pop() {
+            if chunk_map.contains_key(&chunk_pos) && !in_range(&self.origins, chunk_pos, hysteresis)
+            {
+                return Some(chunk_pos);
+            }
+        }
+        None
+    }
+
+    /// Whether `chunk_pos` is still wanted by any viewer.
+    pub fn is_wanted(&self, chunk_pos: ChunkPos, hysteresis: i32) -> bool {
+        in_range(&self.origins, chunk_pos, hysteresis)
+    }
+}
+
+impl ChunkMap {
+    /// Removes `chunk_pos`, saving it to `storage` on `save_tasks` if it was edited.
+    ///
+    /// An edited chunk without a `storage` reloads as generated, so the
+    /// border voxels it changed are put back and shared with the padding of
+    /// loaded neighbours. Returns the neighbours whose padding changed.
+    pub fn unload(
+        &self,
+        chunk_pos: ChunkPos,
+        edited_chunks: &mut EditedChunks,
+        storage: Option<&ChunkStorage>,
+        save_tasks: &mut SaveTasks,
+        block_library: &BlockLibrary,
+    ) -> Vec<ChunkPos> {
+        let Some(original) = edited_chunks.take(chunk_pos) else {
+            self.remove(&chunk_pos);
+            return Vec::new();
+        };
+
+        if let Some(storage) = storage {
+            // neighbours already mirror what will load
+            if let Some((_, chunk)) = self.remove(&chunk_pos) {
+                save_tasks.spawn_task(chunk_pos, chunk, storage.clone(), block_library.clone());
+            }
+            return Vec::new();
+        }
+
+        match self.get_mut(&chunk_pos) {
+            Some(mut chunk) => {
+                for (pos, voxel_opt) in original {
+                    chunk.set(pos, voxel_opt, block_library);
+                }
+            }
+            None => return Vec::new(),
+        }
+
+        let changed = self.share_padding(chunk_pos, block_library);
+        self.remove(&chunk_pos);
+
+        changed
+    }
+}
+
+#[inline]
+fn in_range(origins: &[(ChunkPos, i32)], chunk_pos: ChunkPos, extra: i32) -> bool {
+    origins
+        .iter()
+        .any(|(origin, radius)| Viewer::new(*radius).contains(*origin, chunk_pos, extra))
+}
+
+pub fn stream_chunks(
+    settings: Res<StreamingSettings>,
+    block_library: Res<BlockLibrary>,
+    buffers: Option<(Res<AllocBuffer<VoxelQuad>>, Res<AllocBuffer<ModelQuad>>)>,
+    viewers: Query<(&GlobalTransform, &Viewer)>,
+    terrains: Query<(
+        &GlobalTransform,
+        &ChunkMap,
+        &mut ChunkMeshMap,
+        &mut ChunkStreaming,
+        &mut DirtyChunks,
+        &mut EditedChunks,
+        &mut GenerationTasks,
+        &LightMap,
+        &PendingWrites,
+        &mut SaveTasks,
+        &TerrainGenerator,
+        Option<&ChunkStorage>,
+    )>,
+) {
+    for (
+        terrain_transform,
+        chunk_map,
+        mut chunk_mesh_map,
+        mut streaming,
+        mut dirty_chunks,
+        mut edited_chunks,
+        mut generation_tasks,
+        light_map,
+        pending_writes,
+        mut save_tasks,
+        generator,
+        storage,
+    ) in terrains
+    {
+        let world_to_terrain = terrain_transform.affine().inverse();
+
+        let origins = viewers
+            .iter()
+            .map(|(transform, viewer)| {
+                let point = world_to_terrain.transform_point3(transform.translation());
+                (point_chunk_pos(point), viewer.radius)
+            })
+            .collect::<Vec<_>>();
+
+        if origins != streaming.origins {
+            streaming.rebuild(origins, chunk_map, &settings);
+
+            generation_tasks
+                .retain(|chunk_pos| streaming.is_wanted(chunk_pos, settings.hysteresis));
+        }
+
+        for _ in 0..settings.load_budget {
+            if generation_tasks.len() >= settings.max_generating {
+                break;
+            }
+
+            let Some(chunk_pos) = streaming.next_load(chunk_map, &generation_tasks, &save_tasks)
+            else {
+                break;
+            };
+
+            generation_tasks.spawn_task(
+                chunk_pos,
+                generator.clone(),
+                BlockLibrary::clone(&block_library),
+            );
+        }
+
+        for _ in 0..settings.unload_budget {
+            let Some(chunk_pos) = streaming.next_unload(chunk_map, settings.hysteresis) else {
+                break;
+            };
+
+            let changed = chunk_map.unload(
+                chunk_pos,
+                &mut edited_chunks,
+                storage,
+                &mut save_tasks,
+                &block_library,
+            );
+            dirty_chunks.extend(changed);
+
+            light_map.remove(&chunk_pos);
+            pending_writes.unload(chunk_pos);
+
+            let Some(chunk_mesh) = chunk_mesh_map.remove(&chunk_pos) else {
+                continue;
+            };
+
+            if let Some((alloc_buffer, model_buffer)) = &buffers {
+                chunk_mesh.free(alloc_buffer, model_buffer);
+            }
+        }
+    }
+}
+
+/// Moves finished chunks into the `ChunkMap` and queues them for lighting,
+/// then stores finished meshes in the `ChunkMeshMap`. Chunks that failed
+/// to save are loaded again, still edited.
+pub fn poll_chunk_tasks(
+    block_library: Res<BlockLibrary>,
+    buffers: Option<(Res<AllocBuffer<VoxelQuad>>, Res<AllocBuffer<ModelQuad>>)>,
+    terrains: Query<(
+        &ChunkMap,
+        &mut ChunkMeshMap,
+        &mut DirtyChunks,
+        &mut EditedChunks,
+        &mut GenerationTasks,
+        &mut LightUpdates,
+        &mut MeshingTasks,
+        &PendingWrites,
+        &mut SaveTasks,
+    )>,
+) {
+    for (
+        chunk_map,
+        mut chunk_mesh_map,
+        mut dirty_chunks,
+        mut edited_chunks,
+        mut generation_tasks,
+        mut light_updates,
+        mut meshing_tasks,
+        pending_writes,
+        mut save_tasks,
+    ) in terrains
+    {
+        save_tasks.poll(|chunk_pos, chunk, saved| {
+            let Err(e) = saved else {
+                return;
+            };
+
+            error!("Error {e} saving chunk {chunk_pos}, loading it again");
+
+            // not loaded while saving, see `ChunkStreaming::next_load`
+            chunk_map.insert(chunk_pos, chunk);
+            edited_chunks.insert(chunk_pos);
+            light_updates.chunks.push_back(chunk_pos);
+
+            for neighbour in chunk_map.link_padding(chunk_pos, &block_library) {
+                dirty_chunks.mark(neighbour);
+            }
+        });
+
+        generation_tasks.poll(|chunk_pos, chunk, stored| {
+            chunk_map.insert(chunk_pos, chunk);
+            if stored {
+                pending_writes.mark_stored(chunk_pos);
+            }
+
+            // meshed once lit
+            light_updates.chunks.push_back(chunk_pos);
+
+            // neighbours culled against their old padding
+            for neighbour in chunk_map.link_padding(chunk_pos, &block_library) {
+                dirty_chunks.mark(neighbour);
+            }
+        });
+
+        let Some((alloc_buffer, model_buffer)) = &buffers else {
+            continue;
+        };
+
+        meshing_tasks.poll(|chunk_pos, chunk_mesh_opt| {
+            let Some(chunk_mesh) = chunk_mesh_opt else {
+                return;
+            };
+
+            // unloaded while meshing
+            if !chunk_map.contains_key(&chunk_pos) {
+                chunk_mesh.free(alloc_buffer, model_buffer);
+                return;
+            }
+
+            if let Some(old) = chunk_mesh_map.insert(chunk_pos, chunk_mesh) {
+                old.free(alloc_buffer, model_buffer);
+            }
+        });
+    }
+}
@@ -3,17 +3,44 @@ use bevy::{
     prelude::*,
     render::extract_component::{ExtractComponent, ExtractComponentPlugin},
 };
-use std::{collections::HashSet, sync::Arc};
 
 use crate::{
-    chunk::{Chunk, ChunkMap, ChunkMeshMap, ChunkPos},
-    viewer::Viewer,
+    block_lib::BlockLibrary,
+    chunk::{
+        ChunkLods, ChunkMap, ChunkMeshMap, DirtyChunks, GenerationTasks, LightMap, LightUpdates,
+        LodSettings, MeshingSettings, MeshingTasks, SaveTasks,
+        generator::{
+            PendingWrites, TerrainGenerator,
+            biome::{Biome, BiomeLoader},
+            decoration::apply_pending_writes,
+        },
+        remesh_dirty_chunks, update_light, update_lods,
+    },
+    edit::{ChunkChanged, EditedChunks},
+    history::EditHistory,
+    physics::move_kinematic_controllers,
+    streaming::{ChunkStreaming, StreamingSettings, poll_chunk_tasks, stream_chunks},
 };
 
-#[derive(Component)]
+#[derive(Component, Default)]
+#[require(
+    Transform,
+    ChunkLods,
+    ChunkMap,
+    ChunkMeshMap,
+    ChunkStreaming,
+    DirtyChunks,
+    EditedChunks,
+    EditHistory,
+    GenerationTasks,
+    LightMap,
+    LightUpdates,
+    MeshingTasks,
+    PendingWrites,
+    SaveTasks,
+    TerrainGenerator
+)]
 pub struct Terrain {
-    pub chunk_map: Arc<ChunkMap>,
-    pub chunk_mesh_map: Arc<ChunkMeshMap>,
     // todo: stop cloning this whole thing every frame
     pub visible_quad_ranges: Vec<Vec<(u32, u32)>>,
 }
@@ -69,26 +96,33 @@ impl ExtractComponent for Terrain {
 //     }
 // }
 
-pub fn visibile(
-    terrains: Query<&Terrain>,
-    viewers: Query<(&Transform, &Viewer), Changed<Transform>>,
-) {
-    for (transform, viewer) in viewers {
-        let chunk_pos = ChunkPos::from_world(transform.translation);
-        let visible_positions = viewer.visible_positions(chunk_pos);
-
-        for position in visible_positions {
-            for terrain in terrains {
-                let chunk = terrain.chunk_map.get(&position);
-            }
-        }
-    }
-}
-
 pub struct TerrainPlugin;
 
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(ExtractComponentPlugin::<TerrainMesh>::default());
+        app.add_plugins(ExtractComponentPlugin::<Terrain>::default())
+            .init_asset::<Biome>()
+            .init_asset_loader::<BiomeLoader>()
+            .init_resource::<StreamingSettings>()
+            .init_resource::<MeshingSettings>()
+            .init_resource::<LodSettings>()
+            .add_message::<ChunkChanged>()
+            .add_systems(
+                Update,
+                (
+                    stream_chunks,
+                    poll_chunk_tasks,
+                    apply_pending_writes,
+                    update_light,
+                    update_lods,
+                    remesh_dirty_chunks,
+                )
+                    .chain()
+                    .run_if(resource_exists::<BlockLibrary>),
+            )
+            .add_systems(
+                Update,
+                move_kinematic_controllers.run_if(resource_exists::<BlockLibrary>),
+            );
     }
 }
@@ -12,6 +12,12 @@ impl Viewer {
         Self { radius }
     }
 
+    /// Whether `chunk_pos` is within `radius + extra` of `origin`.
+    #[inline]
+    pub fn contains(&self, origin: ChunkPos, chunk_pos: ChunkPos, extra: i32) -> bool {
+        (chunk_pos - origin).length_squared() <= (self.radius + extra).pow(2)
+    }
+
     pub fn visible_positions(&self, origin: ChunkPos) -> impl Iterator<Item = ChunkPos> {
         let radius = self.radius;
         let radius_sq = radius.pow(2);
@@ -21,7 +27,7 @@ impl Viewer {
                 (-radius..=radius).filter_map(move |z| {
                     let offset = IVec3::new(x, y, z);
                     if offset.length_squared() <= radius_sq {
-                        Some((origin.0 + offset).into())
+                        Some(origin + offset)
                     } else {
                         None
                     }
@@ -0,0 +1,107 @@
+// Helpers shared by the integration tests, each test crate uses some.
+#![allow(dead_code)]
+
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::math::IVec3;
+use enum_map::enum_map;
+use voxel::{
+    block_lib::{Block, BlockLibrary, InnerBlockLibrary, state::BlockState},
+    chunk::{Chunk, ChunkMap, ChunkPos, VoxelIndex, Voxels, generator::from_fn, pad::VOL},
+};
+
+/// An opaque cube.
+pub fn block() -> Block {
+    Block {
+        display_name: String::new(),
+        collision_aabbs: Vec::new(),
+        is_transparent: false,
+        connects_to_same: false,
+        emission: 0,
+        light_opacity: 15,
+        textures: enum_map! { _ => 0 },
+        model: None,
+        state: BlockState::default(),
+    }
+}
+
+pub fn block_library<'a>(entries: impl IntoIterator<Item = (&'a str, Block)>) -> BlockLibrary {
+    BlockLibrary(Arc::new(InnerBlockLibrary::from_blocks(entries)))
+}
+
+/// A chunk of `f(index)` for every padded index.
+pub fn chunk_from_fn(
+    block_library: &BlockLibrary,
+    f: impl Fn(usize) -> Option<VoxelIndex>,
+) -> Chunk {
+    let mut voxels = Voxels::EMPTY;
+    for index in 0..VOL {
+        voxels.set(index, f(index));
+    }
+    voxels.compact();
+
+    Chunk::from_voxels(voxels, block_library)
+}
+
+/// Chunks at `chunks` of the block named `f(voxel_pos)`, padding included.
+pub fn world(
+    block_library: &BlockLibrary,
+    chunks: impl IntoIterator<Item = ChunkPos>,
+    f: impl Fn(IVec3) -> Option<&'static str>,
+) -> ChunkMap {
+    let chunk_map = ChunkMap::default();
+
+    for chunk_pos in chunks {
+        let chunk = from_fn(chunk_pos, block_library, |voxel_pos| {
+            f(voxel_pos).and_then(|name| block_library.lookup(name))
+        });
+        chunk_map.insert(chunk_pos, chunk);
+    }
+
+    chunk_map
+}
+
+/// Chunks `-1..=1` on every axis.
+pub fn around_origin() -> impl Iterator<Item = ChunkPos> {
+    (-1..=1).flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| IVec3::new(x, y, z))))
+}
+
+pub fn assert_same_voxels(a: &Chunk, b: &Chunk) {
+    for index in 0..VOL {
+        assert_eq!(
+            a.voxels().get(index),
+            b.voxels().get(index),
+            "voxel {index}"
+        );
+    }
+}
+
+/// A directory under the system temp directory, removed on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("voxel-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,196 @@
+mod common;
+
+use bevy::{
+    ecs::system::RunSystemOnce,
+    math::IVec3,
+    prelude::{Entity, World},
+};
+use voxel::{
+    block_lib::BlockLibrary,
+    chunk::{
+        ChunkMap, ChunkPos, DirtyChunks, GenerationTasks, LightUpdates, VoxelIndex,
+        generator::{
+            DecoratedGenerator, Feature, FlatGenerator, PendingWrites, TerrainGenerator,
+            decoration::{Decorator, Placement, apply_pending_writes},
+        },
+    },
+    edit::EditedChunks,
+};
+
+use common::{block, block_library};
+
+// The chunk at the origin spills a log onto each side of its `pos_x`
+// border, into the padding of its own chunk and into `TARGET`.
+
+const SOURCE: ChunkPos = IVec3::ZERO;
+const TARGET: ChunkPos = IVec3::X;
+const SPILLED: [IVec3; 2] = [IVec3::new(63, 5, 5), IVec3::new(64, 5, 5)];
+
+struct Spill;
+
+impl Feature for Spill {
+    fn decorate(&self, decorator: &mut Decorator) {
+        if decorator.chunk_pos != SOURCE {
+            return;
+        }
+
+        let log = decorator.block_library.lookup("test:log");
+        for voxel_pos in SPILLED {
+            decorator.set(voxel_pos, log, Placement::Replace);
+        }
+    }
+}
+
+struct Terrain {
+    world: World,
+    entity: Entity,
+    generator: TerrainGenerator,
+    pending: PendingWrites,
+}
+
+impl Terrain {
+    fn new() -> Self {
+        let mut world = World::new();
+        world.insert_resource(block_library([
+            ("test:stone", block()),
+            ("test:log", block()),
+        ]));
+
+        let pending = PendingWrites::default();
+        let generator = TerrainGenerator::new(
+            0,
+            DecoratedGenerator::new(FlatGenerator::new(0, "test:stone"), pending.clone())
+                .with_feature(Spill),
+        );
+
+        let entity = world
+            .spawn((
+                ChunkMap::default(),
+                pending.clone(),
+                EditedChunks::default(),
+                GenerationTasks::default(),
+                DirtyChunks::default(),
+                LightUpdates::default(),
+            ))
+            .id();
+
+        Self {
+            world,
+            entity,
+            generator,
+            pending,
+        }
+    }
+
+    fn block_library(&self) -> BlockLibrary {
+        self.world.resource::<BlockLibrary>().clone()
+    }
+
+    fn chunk_map(&self) -> &ChunkMap {
+        self.world.get::<ChunkMap>(self.entity).unwrap()
+    }
+
+    fn log(&self) -> Option<VoxelIndex> {
+        self.block_library().lookup("test:log")
+    }
+
+    /// Generates `chunk_pos` and delivers what is pending for loaded chunks.
+    fn load(&mut self, chunk_pos: ChunkPos) {
+        let block_library = self.block_library();
+        let chunk = self.generator.generate(chunk_pos, &block_library);
+
+        let chunk_map = self.chunk_map();
+        chunk_map.insert(chunk_pos, chunk);
+        chunk_map.link_padding(chunk_pos, &block_library);
+
+        self.world.run_system_once(apply_pending_writes).unwrap();
+    }
+
+    fn unload(&mut self, chunk_pos: ChunkPos) {
+        self.chunk_map().remove(&chunk_pos);
+        self.pending.unload(chunk_pos);
+    }
+
+    fn has_logs(&self) -> bool {
+        let log = self.log();
+        SPILLED
+            .iter()
+            .all(|voxel_pos| self.chunk_map().get_voxel(*voxel_pos) == Some(log))
+    }
+}
+
+#[test]
+fn writes_reach_chunks_loaded_before_their_source() {
+    let mut terrain = Terrain::new();
+
+    terrain.load(TARGET);
+    assert!(!terrain.has_logs());
+
+    terrain.load(SOURCE);
+    assert!(terrain.has_logs());
+}
+
+#[test]
+fn regenerated_targets_get_writes_again() {
+    for order in [[SOURCE, TARGET], [TARGET, SOURCE]] {
+        let mut terrain = Terrain::new();
+        for chunk_pos in order {
+            terrain.load(chunk_pos);
+        }
+        assert!(terrain.has_logs());
+
+        // the source stays loaded and doesn't spill again
+        terrain.unload(TARGET);
+        terrain.load(TARGET);
+        assert!(terrain.has_logs());
+    }
+}
+
+#[test]
+fn regenerated_sources_replace_their_writes() {
+    let mut terrain = Terrain::new();
+
+    for _ in 0..3 {
+        terrain.load(SOURCE);
+        assert_eq!(terrain.pending.len(), 1);
+        assert_eq!(terrain.pending.writes(SOURCE, TARGET).len(), 2);
+    }
+
+    // writes into chunks that never load go with their source
+    terrain.unload(SOURCE);
+    assert!(terrain.pending.is_empty());
+}
+
+#[test]
+fn edited_chunks_keep_their_edits() {
+    let mut terrain = Terrain::new();
+    terrain.load(TARGET);
+    terrain.load(SOURCE);
+
+    let block_library = terrain.block_library();
+    let mut changed = DirtyChunks::default();
+    terrain
+        .chunk_map()
+        .set_voxel(SPILLED[1], None, &block_library, &mut changed, None);
+    terrain
+        .world
+        .get_mut::<EditedChunks>(terrain.entity)
+        .unwrap()
+        .extend(changed.iter().copied());
+
+    terrain.unload(SOURCE);
+    terrain.load(SOURCE);
+
+    assert_eq!(terrain.chunk_map().get_voxel(SPILLED[1]), Some(None));
+}
+
+#[test]
+fn stored_chunks_take_no_writes() {
+    let mut terrain = Terrain::new();
+
+    terrain.load(TARGET);
+    terrain.pending.mark_stored(TARGET);
+    terrain.load(SOURCE);
+
+    assert_eq!(terrain.chunk_map().get_voxel(SPILLED[1]), Some(None));
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use bevy::{
+    ecs::{message::Messages, system::RunSystemOnce},
+    math::{IVec3, UVec3},
+    prelude::{Entity, MessageReader, World},
+};
+use voxel::{
+    block_lib::BlockLibrary,
+    chunk::{ChunkMap, ChunkPos, DirtyChunks, LightUpdates, generator::from_fn},
+    edit::{ChunkChanged, EditedChunks, TerrainEditor},
+    history::EditHistory,
+};
+
+use common::{block, block_library};
+
+// Stone up to `y = 10` in the chunk at the origin and the one past it on
+// `x`. `BORDER` is on the `pos_x` border of the first, mirrored into the
+// padding of the second.
+
+const CHUNK: ChunkPos = IVec3::ZERO;
+const NEIGHBOUR: ChunkPos = IVec3::X;
+const BORDER: IVec3 = IVec3::new(62, 5, 5);
+/// `BORDER` in the padding of `NEIGHBOUR`.
+const PADDING: UVec3 = UVec3::new(0, 5, 5);
+
+struct Terrain {
+    world: World,
+    entity: Entity,
+}
+
+impl Terrain {
+    fn new() -> Self {
+        let block_library = block_library([("test:stone", block())]);
+        let stone = block_library.lookup("test:stone");
+
+        let chunk_map = ChunkMap::default();
+        for chunk_pos in [CHUNK, NEIGHBOUR] {
+            let chunk = from_fn(chunk_pos, &block_library, |voxel_pos| {
+                (voxel_pos.y <= 10).then_some(stone).flatten()
+            });
+            chunk_map.insert(chunk_pos, chunk);
+            chunk_map.link_padding(chunk_pos, &block_library);
+        }
+
+        let mut world = World::new();
+        world.insert_resource(block_library);
+        world.init_resource::<Messages<ChunkChanged>>();
+
+        let entity = world
+            .spawn((
+                chunk_map,
+                DirtyChunks::default(),
+                EditedChunks::default(),
+                EditHistory::default(),
+                LightUpdates::default(),
+            ))
+            .id();
+
+        Self { world, entity }
+    }
+
+    fn edit<T: 'static>(
+        &mut self,
+        f: impl Fn(&mut TerrainEditor, Entity) -> T + Send + Sync + 'static,
+    ) -> T {
+        let entity = self.entity;
+        self.world
+            .run_system_once(move |mut editor: TerrainEditor| f(&mut editor, entity))
+            .unwrap()
+    }
+
+    fn carve(&mut self, voxel_pos: IVec3) -> bool {
+        self.edit(move |editor, terrain| editor.set(terrain, voxel_pos, None))
+    }
+
+    fn undo(&mut self) -> bool {
+        self.edit(|editor, terrain| editor.undo(terrain))
+    }
+
+    fn redo(&mut self) -> bool {
+        self.edit(|editor, terrain| editor.redo(terrain))
+    }
+
+    fn chunk_map(&self) -> &ChunkMap {
+        self.world.get::<ChunkMap>(self.entity).unwrap()
+    }
+
+    fn is_stone(&self, voxel_pos: IVec3) -> bool {
+        self.chunk_map().get_voxel(voxel_pos).unwrap().is_some()
+    }
+
+    /// Chunks of every `ChunkChanged` written so far.
+    fn changed_chunks(&mut self) -> Vec<ChunkPos> {
+        self.world
+            .run_system_once(|mut chunk_changed: MessageReader<ChunkChanged>| {
+                chunk_changed
+                    .read()
+                    .map(|message| message.chunk_pos)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap()
+    }
+}
+
+#[test]
+fn transactions_undo_together() {
+    let mut terrain = Terrain::new();
+    let voxels = [IVec3::new(5, 5, 5), IVec3::new(6, 5, 5)];
+
+    terrain.edit(|editor, terrain| editor.begin(terrain));
+    for voxel_pos in voxels {
+        assert!(terrain.carve(voxel_pos));
+    }
+    terrain.edit(|editor, terrain| editor.commit(terrain));
+
+    assert!(terrain.undo());
+    assert!(voxels.iter().all(|voxel_pos| terrain.is_stone(*voxel_pos)));
+    assert!(!terrain.undo());
+
+    assert!(terrain.redo());
+    assert!(voxels.iter().all(|voxel_pos| !terrain.is_stone(*voxel_pos)));
+}
+
+#[test]
+fn edits_clear_the_redo_stack() {
+    let mut terrain = Terrain::new();
+
+    terrain.carve(IVec3::new(5, 5, 5));
+    assert!(terrain.undo());
+
+    terrain.carve(IVec3::new(6, 5, 5));
+    assert!(!terrain.redo());
+    assert!(terrain.is_stone(IVec3::new(5, 5, 5)));
+}
+
+#[test]
+fn undo_restores_the_padding_of_neighbours() {
+    let mut terrain = Terrain::new();
+    let padding_is_stone = |terrain: &Terrain| {
+        let neighbour = terrain.chunk_map().get(&NEIGHBOUR).unwrap();
+        neighbour.get(PADDING).is_some()
+    };
+
+    terrain.carve(BORDER);
+    assert!(!padding_is_stone(&terrain));
+
+    assert!(terrain.undo());
+    assert!(terrain.is_stone(BORDER));
+    assert!(padding_is_stone(&terrain));
+}
+
+#[test]
+fn undo_skips_unloaded_chunks() {
+    let mut terrain = Terrain::new();
+    let loaded = IVec3::new(5, 5, 5);
+
+    terrain.edit(|editor, terrain| editor.begin(terrain));
+    terrain.carve(loaded);
+    terrain.carve(IVec3::new(70, 5, 5));
+    terrain.edit(|editor, terrain| editor.commit(terrain));
+
+    terrain.chunk_map().remove(&NEIGHBOUR);
+
+    assert!(terrain.undo());
+    assert!(terrain.is_stone(loaded));
+    assert!(!terrain.chunk_map().contains_key(&NEIGHBOUR));
+}
+
+#[test]
+fn chunk_changed_is_written_once_per_chunk() {
+    let mut terrain = Terrain::new();
+
+    // across the border, changing the padding of both chunks
+    let count = terrain.edit(|editor, terrain| {
+        editor.fill_box(terrain, IVec3::new(58, 5, 5), IVec3::new(66, 6, 6), None)
+    });
+    assert_eq!(count, 9 * 2 * 2);
+
+    let mut changed = terrain.changed_chunks();
+    changed.sort_by_key(|chunk_pos| chunk_pos.to_array());
+    assert_eq!(changed, vec![CHUNK, NEIGHBOUR]);
+}
@@ -0,0 +1,244 @@
+mod common;
+
+use bevy::{
+    ecs::system::RunSystemOnce,
+    math::{IVec3, UVec3},
+    prelude::{Entity, World},
+};
+use voxel::{
+    block_lib::BlockLibrary,
+    chunk::{
+        ChunkMap, ChunkPos, DirtyChunks, LightChannel, LightMap, LightUpdates, MAX_LIGHT,
+        generator::from_fn, update_light,
+    },
+};
+
+use common::{block, block_library};
+
+// Terrains lit by running `update_light` until every inserted chunk is
+// lit. Nothing is loaded above the chunks, so sky light enters their tops.
+
+struct Terrain {
+    world: World,
+    entity: Entity,
+}
+
+impl Terrain {
+    fn new() -> Self {
+        let mut lamp = block();
+        lamp.emission = 14;
+
+        let mut world = World::new();
+        world.insert_resource(block_library([
+            ("test:stone", block()),
+            ("test:lamp", lamp),
+        ]));
+
+        let entity = world
+            .spawn((
+                ChunkMap::default(),
+                LightMap::default(),
+                LightUpdates::default(),
+                DirtyChunks::default(),
+            ))
+            .id();
+
+        Self { world, entity }
+    }
+
+    fn block_library(&self) -> BlockLibrary {
+        self.world.resource::<BlockLibrary>().clone()
+    }
+
+    fn chunk_map(&self) -> &ChunkMap {
+        self.world.get::<ChunkMap>(self.entity).unwrap()
+    }
+
+    fn light_updates(&mut self) -> &mut LightUpdates {
+        self.world
+            .get_mut::<LightUpdates>(self.entity)
+            .unwrap()
+            .into_inner()
+    }
+
+    /// Inserts a chunk of `f(voxel_pos)` and lights it.
+    fn load(&mut self, chunk_pos: ChunkPos, f: impl Fn(IVec3) -> Option<&'static str>) {
+        let block_library = self.block_library();
+        let chunk = from_fn(chunk_pos, &block_library, |voxel_pos| {
+            f(voxel_pos).and_then(|name| block_library.lookup(name))
+        });
+
+        let chunk_map = self.chunk_map();
+        chunk_map.insert(chunk_pos, chunk);
+        chunk_map.link_padding(chunk_pos, &block_library);
+
+        self.light_updates().chunks.push_back(chunk_pos);
+        self.update();
+    }
+
+    /// Replaces the block at `voxel_pos` and relights around it.
+    fn set(&mut self, voxel_pos: IVec3, name: Option<&str>) {
+        let block_library = self.block_library();
+        let voxel_opt = name.and_then(|name| block_library.lookup(name));
+
+        let mut dirty_chunks = DirtyChunks::default();
+        self.chunk_map().set_voxel(
+            voxel_pos,
+            voxel_opt,
+            &block_library,
+            &mut dirty_chunks,
+            None,
+        );
+
+        self.light_updates().voxels.push(voxel_pos);
+        self.update();
+    }
+
+    fn update(&mut self) {
+        loop {
+            self.world.run_system_once(update_light).unwrap();
+
+            let light_updates = self.light_updates();
+            if light_updates.chunks.is_empty() && light_updates.voxels.is_empty() {
+                break;
+            }
+        }
+    }
+
+    fn light_map(&self) -> &LightMap {
+        self.world.get::<LightMap>(self.entity).unwrap()
+    }
+
+    fn level(&self, voxel_pos: IVec3, channel: LightChannel) -> u8 {
+        channel.get(self.light_map().get_light(voxel_pos).unwrap())
+    }
+
+    fn sky(&self, voxel_pos: IVec3) -> u8 {
+        self.level(voxel_pos, LightChannel::Sky)
+    }
+
+    fn block(&self, voxel_pos: IVec3) -> u8 {
+        self.level(voxel_pos, LightChannel::Block)
+    }
+}
+
+/// Stone at and below `y = 10`.
+fn floor(voxel_pos: IVec3) -> Option<&'static str> {
+    (voxel_pos.y <= 10).then_some("test:stone")
+}
+
+#[test]
+fn sky_light_falls_through_open_air() {
+    let mut terrain = Terrain::new();
+    terrain.load(IVec3::ZERO, floor);
+
+    for y in 11..=62 {
+        assert_eq!(terrain.sky(IVec3::new(5, y, 5)), MAX_LIGHT, "y = {y}");
+    }
+    assert_eq!(terrain.sky(IVec3::new(5, 10, 5)), 0);
+}
+
+#[test]
+fn sky_light_spreads_under_a_roof() {
+    let mut terrain = Terrain::new();
+
+    // a roof over `10..=20` on `x` and `z`, open sky just past its edges
+    terrain.load(IVec3::ZERO, |voxel_pos| {
+        let roof = voxel_pos.y == 40
+            && (10..=20).contains(&voxel_pos.x)
+            && (10..=20).contains(&voxel_pos.z);
+        (roof || voxel_pos.y <= 10).then_some("test:stone")
+    });
+
+    assert_eq!(terrain.sky(IVec3::new(9, 30, 15)), MAX_LIGHT);
+    assert_eq!(terrain.sky(IVec3::new(10, 30, 15)), MAX_LIGHT - 1);
+    assert_eq!(terrain.sky(IVec3::new(15, 30, 15)), MAX_LIGHT - 6);
+    assert_eq!(terrain.sky(IVec3::new(15, 40, 15)), 0);
+}
+
+#[test]
+fn block_light_falls_off_by_one_per_voxel() {
+    let lamp = IVec3::new(20, 20, 20);
+
+    let mut terrain = Terrain::new();
+    terrain.load(IVec3::ZERO, |voxel_pos| {
+        (voxel_pos == lamp).then_some("test:lamp")
+    });
+
+    assert_eq!(terrain.block(lamp), 14);
+    for distance in 1..=14 {
+        let expected = 14 - distance as u8;
+        assert_eq!(terrain.block(lamp + IVec3::X * distance), expected);
+        assert_eq!(terrain.block(lamp - IVec3::Y * distance), expected);
+    }
+
+    // by manhattan distance around corners
+    assert_eq!(terrain.block(lamp + IVec3::new(2, 1, -3)), 8);
+}
+
+#[test]
+fn light_crosses_chunk_borders() {
+    let lamp = IVec3::new(60, 20, 20);
+    let blocks = |voxel_pos: IVec3| (voxel_pos == lamp).then_some("test:lamp");
+
+    let mut terrain = Terrain::new();
+    terrain.load(IVec3::ZERO, blocks);
+
+    // the chunk past `x = 62` is lit from the one already loaded
+    terrain.load(IVec3::X, blocks);
+    assert_eq!(terrain.block(IVec3::new(65, 20, 20)), 9);
+
+    // and mirrored into the padding of the lamp's chunk
+    let light_map = terrain.light_map();
+    let light = light_map
+        .get(&IVec3::ZERO)
+        .unwrap()
+        .get(UVec3::new(63, 20, 20));
+    assert_eq!(LightChannel::Block.get(light), 11);
+
+    // sky light reaches the top of both chunks
+    assert_eq!(terrain.sky(IVec3::new(65, 62, 20)), MAX_LIGHT);
+}
+
+#[test]
+fn removing_a_lamp_removes_its_light() {
+    let lamps = [IVec3::new(20, 20, 20), IVec3::new(30, 20, 20)];
+
+    let mut terrain = Terrain::new();
+    terrain.load(IVec3::ZERO, |voxel_pos| {
+        lamps.contains(&voxel_pos).then_some("test:lamp")
+    });
+    assert_eq!(terrain.block(IVec3::new(18, 20, 20)), 12);
+
+    terrain.set(lamps[0], None);
+
+    // only what the other lamp reaches is left
+    assert_eq!(terrain.block(lamps[0]), 4);
+    assert_eq!(terrain.block(IVec3::new(18, 20, 20)), 2);
+    assert_eq!(terrain.block(IVec3::new(10, 20, 20)), 0);
+    assert_eq!(terrain.block(lamps[1]), 14);
+
+    terrain.set(lamps[1], None);
+    for x in 10..=40 {
+        assert_eq!(terrain.block(IVec3::new(x, 20, 20)), 0, "x = {x}");
+    }
+}
+
+#[test]
+fn placing_a_block_casts_a_shadow() {
+    let mut terrain = Terrain::new();
+    terrain.load(IVec3::ZERO, floor);
+
+    let roof = IVec3::new(5, 30, 5);
+    terrain.set(roof, Some("test:stone"));
+
+    assert_eq!(terrain.sky(roof), 0);
+    assert_eq!(terrain.sky(roof + IVec3::Y), MAX_LIGHT);
+
+    // lit from the open columns beside it
+    assert_eq!(terrain.sky(roof - IVec3::Y), MAX_LIGHT - 1);
+    assert_eq!(terrain.sky(IVec3::new(5, 11, 5)), MAX_LIGHT - 1);
+
+    terrain.set(roof, None);
+    assert_eq!(terrain.sky(roof - IVec3::Y), MAX_LIGHT);
+}
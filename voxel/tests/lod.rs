@@ -0,0 +1,144 @@
+mod common;
+
+use bevy::math::{IVec3, UVec3};
+use voxel::{
+    block_lib::BlockLibrary,
+    chunk::{LodPos, generator::from_fn},
+};
+
+use common::{block, block_library, world};
+
+// Downsampled voxel `p` of a level `L` region at the origin stands for
+// voxels `(p - 1) * 2^L + 1..=p * 2^L` along each axis.
+
+fn library() -> BlockLibrary {
+    block_library([("test:stone", block()), ("test:dirt", block())])
+}
+
+fn region(level: u8) -> LodPos {
+    LodPos {
+        level,
+        pos: IVec3::ZERO,
+    }
+}
+
+fn unlinked(_: IVec3) -> bool {
+    false
+}
+
+#[test]
+fn floors_keep_their_height() {
+    let block_library = library();
+    let stone = block_library.lookup("test:stone");
+
+    let chunks = (0..8).map(|i| IVec3::new(i & 1, (i >> 1) & 1, i >> 2));
+    let chunk_map = world(&block_library, chunks, |voxel_pos| {
+        (voxel_pos.y <= 20).then_some("test:stone")
+    });
+
+    let chunk = chunk_map.downsample(region(1), &block_library, unlinked);
+
+    assert_eq!(chunk.get(UVec3::new(5, 10, 5)), stone);
+    assert_eq!(chunk.get(UVec3::new(5, 11, 5)), None);
+    assert_eq!(chunk.get(UVec3::new(40, 10, 40)), stone);
+    assert_eq!(chunk.get(UVec3::new(40, 11, 40)), None);
+}
+
+#[test]
+fn uniform_chunks() {
+    let block_library = library();
+    let stone = block_library.lookup("test:stone");
+
+    // solid up to `x = 62`, then empty
+    let chunk_map = world(&block_library, [IVec3::ZERO], |_| Some("test:stone"));
+    chunk_map.insert(IVec3::X, from_fn(IVec3::X, &block_library, |_| None));
+
+    for chunk_pos in [IVec3::ZERO, IVec3::X] {
+        let chunk = chunk_map.get(&chunk_pos).unwrap();
+        assert!(chunk.voxels().uniform().is_some());
+    }
+
+    let chunk = chunk_map.downsample(region(1), &block_library, unlinked);
+
+    assert_eq!(chunk.get(UVec3::new(31, 5, 5)), stone);
+    assert_eq!(chunk.get(UVec3::new(32, 5, 5)), None);
+}
+
+#[test]
+fn most_common_block_if_half_filled() {
+    let block_library = library();
+    let stone = block_library.lookup("test:stone");
+    let dirt = block_library.lookup("test:dirt");
+
+    // within `9..=10` on `y` and `z`
+    let chunk_map = world(&block_library, [IVec3::ZERO], |voxel_pos| {
+        if !(9..=10).contains(&voxel_pos.y) || !(9..=10).contains(&voxel_pos.z) {
+            return None;
+        }
+
+        let corner = voxel_pos.y == 9 && voxel_pos.z == 9;
+        match voxel_pos.x {
+            // three dirt and a stone
+            9 if corner => Some("test:stone"),
+            9 => Some("test:dirt"),
+            // three stone
+            19 if !corner => Some("test:stone"),
+            // four stone
+            29 => Some("test:stone"),
+            _ => None,
+        }
+    });
+
+    let chunk = chunk_map.downsample(region(1), &block_library, unlinked);
+
+    assert_eq!(chunk.get(UVec3::new(5, 5, 5)), dirt);
+    assert_eq!(chunk.get(UVec3::new(10, 5, 5)), None);
+    assert_eq!(chunk.get(UVec3::new(15, 5, 5)), stone);
+}
+
+#[test]
+fn voxels_crossing_chunk_borders() {
+    let block_library = library();
+    let stone = block_library.lookup("test:stone");
+    let dirt = block_library.lookup("test:dirt");
+
+    // voxel `16` stands for `61..=64` along `x`, split between two chunks
+    let chunk_map = world(&block_library, [IVec3::ZERO, IVec3::X], |voxel_pos| {
+        if !(5..=8).contains(&voxel_pos.y) || !(5..=8).contains(&voxel_pos.z) {
+            return None;
+        }
+
+        match voxel_pos.x {
+            61 => Some("test:dirt"),
+            63..=64 => Some("test:stone"),
+            _ => None,
+        }
+    });
+
+    let pos = UVec3::new(16, 2, 2);
+    let chunk = chunk_map.downsample(region(2), &block_library, unlinked);
+    assert_eq!(chunk.get(pos), stone);
+
+    // unloaded voxels don't count
+    chunk_map.remove(&IVec3::X);
+    let chunk = chunk_map.downsample(region(2), &block_library, unlinked);
+    assert_eq!(chunk.get(pos), dirt);
+}
+
+#[test]
+fn padding_is_only_filled_toward_linked_faces() {
+    let block_library = library();
+    let stone = block_library.lookup("test:stone");
+
+    let chunk_map = world(&block_library, [IVec3::NEG_X, IVec3::ZERO], |voxel_pos| {
+        (voxel_pos.y <= 20).then_some("test:stone")
+    });
+
+    let chunk = chunk_map.downsample(region(1), &block_library, |offset| offset == IVec3::NEG_X);
+    assert_eq!(chunk.get(UVec3::new(0, 5, 5)), stone);
+    assert_eq!(chunk.get(UVec3::new(1, 5, 5)), stone);
+
+    let chunk = chunk_map.downsample(region(1), &block_library, unlinked);
+    assert_eq!(chunk.get(UVec3::new(0, 5, 5)), None);
+    assert_eq!(chunk.get(UVec3::new(1, 5, 5)), stone);
+}
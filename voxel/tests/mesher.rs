@@ -0,0 +1,425 @@
+mod common;
+
+use std::sync::Arc;
+
+use bevy::{
+    math::{IVec3, UVec3},
+    platform::collections::HashSet,
+};
+use enum_map::enum_map;
+use proptest::{collection::vec, prelude::*};
+use voxel::{
+    block_lib::{Block, BlockLibrary, model::BlockModel},
+    chunk::{Chunk, Mesher, ModelMesher, VoxelIndex, VoxelQuad, Voxels, chunk_origin, pad},
+    math::signed_axis::*,
+};
+
+use common::{block, block_library};
+
+// `reference_faces` culls one face at a time straight from the voxels,
+// following the rules in `mesher.rs`, and the `Mesher` output is expanded
+// back into faces to compare. A face covered twice is an overlap, one
+// missing is a gap, one extra is a merge that went too far. Model voxels
+// are left to the `ModelMesher` and never cull their neighbours.
+
+const BLOCKS: usize = 6;
+const SLAB: usize = 5;
+
+/// A face of the voxel at `voxel_pos` toward `signed_axis`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Face {
+    signed_axis: SignedAxis,
+    transparent: bool,
+    voxel_pos: IVec3,
+    texture_index: u32,
+}
+
+/// A cube with `texture` on every face, see-through if `is_transparent`.
+fn textured(texture: u32, is_transparent: bool, connects_to_same: bool) -> Block {
+    Block {
+        is_transparent,
+        connects_to_same,
+        light_opacity: if is_transparent { 0 } else { 15 },
+        textures: enum_map! { _ => texture },
+        ..block()
+    }
+}
+
+fn library() -> BlockLibrary {
+    // a block textured differently per face catches quads facing the wrong way
+    let mut log = textured(4, false, false);
+    log.textures[PosY] = 5;
+    log.textures[NegY] = 6;
+
+    let mut slab = textured(7, false, false);
+    slab.model = BlockModel::Slab.faces(&slab.textures).map(Arc::from);
+
+    block_library([
+        ("test:stone", textured(0, false, false)),
+        ("test:dirt", textured(1, false, false)),
+        ("test:glass", textured(2, true, true)),
+        ("test:leaves", textured(3, true, false)),
+        ("test:log", log),
+        ("test:slab", slab),
+    ])
+}
+
+#[derive(Debug, Clone)]
+struct ChunkSpec {
+    fill: Option<usize>,
+    boxes: Vec<(UVec3, UVec3, Option<usize>)>,
+    voxels: Vec<(UVec3, Option<usize>)>,
+}
+
+impl ChunkSpec {
+    fn build(&self, block_library: &BlockLibrary) -> Chunk {
+        let mut voxels = Voxels::Uniform(self.fill.and_then(VoxelIndex::new));
+
+        for (min, size, voxel) in &self.boxes {
+            let max = (*min + *size).min(UVec3::splat(pad::LEN as u32));
+            for z in min.z..max.z {
+                for y in min.y..max.y {
+                    for x in min.x..max.x {
+                        let index = pad::linearize(UVec3::new(x, y, z));
+                        voxels.set(index, voxel.and_then(VoxelIndex::new));
+                    }
+                }
+            }
+        }
+
+        for (pos, voxel) in &self.voxels {
+            voxels.set(pad::linearize(*pos), voxel.and_then(VoxelIndex::new));
+        }
+
+        Chunk::from_voxels(voxels, block_library)
+    }
+}
+
+fn arb_voxel() -> impl Strategy<Value = Option<usize>> {
+    prop_oneof![
+        2 => Just(None),
+        3 => (0..BLOCKS).prop_map(Some),
+    ]
+}
+
+fn arb_pos() -> impl Strategy<Value = UVec3> {
+    let len = pad::LEN as u32;
+    (0..len, 0..len, 0..len).prop_map(|(x, y, z)| UVec3::new(x, y, z))
+}
+
+fn arb_size() -> impl Strategy<Value = UVec3> {
+    (1..24u32, 1..24u32, 1..24u32).prop_map(|(x, y, z)| UVec3::new(x, y, z))
+}
+
+fn arb_chunk() -> impl Strategy<Value = ChunkSpec> {
+    (
+        arb_voxel(),
+        vec((arb_pos(), arb_size(), arb_voxel()), 0..12),
+        vec((arb_pos(), arb_voxel()), 0..256),
+    )
+        .prop_map(|(fill, boxes, voxels)| ChunkSpec {
+            fill,
+            boxes,
+            voxels,
+        })
+}
+
+/// Every visible face of the unpadded voxels, one at a time.
+fn reference_faces(chunk: &Chunk, chunk_pos: IVec3, block_library: &BlockLibrary) -> HashSet<Face> {
+    let origin = chunk_origin(chunk_pos);
+    let mut faces = HashSet::new();
+
+    for z in 1..pad::LEN as u32 - 1 {
+        for y in 1..pad::LEN as u32 - 1 {
+            for x in 1..pad::LEN as u32 - 1 {
+                let pos = UVec3::new(x, y, z);
+                let Some(voxel) = chunk.get(pos) else {
+                    continue;
+                };
+                let block = &block_library[voxel];
+                if block.model.is_some() {
+                    continue;
+                }
+
+                for signed_axis in SignedAxis::ALL {
+                    let adj_pos =
+                        (pos.as_ivec3() + IVec3::from_array(signed_axis.coords())).as_uvec3();
+                    let adj_opt = chunk.get(adj_pos);
+                    let adj_opaque = adj_opt.is_some_and(|adj| {
+                        !block_library[adj].is_transparent && block_library[adj].model.is_none()
+                    });
+
+                    let visible = if block.is_transparent {
+                        !adj_opaque && (adj_opt != Some(voxel) || !block.connects_to_same)
+                    } else {
+                        !adj_opaque
+                    };
+
+                    if visible {
+                        faces.insert(Face {
+                            signed_axis,
+                            transparent: block.is_transparent,
+                            voxel_pos: origin + pos.as_ivec3(),
+                            texture_index: block.textures[signed_axis],
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    faces
+}
+
+/// Expands the quads of `Mesher::mesh` into faces, failing on overlaps
+/// and on quads in the wrong range.
+fn mesher_faces(
+    chunk: &Chunk,
+    chunk_pos: IVec3,
+    block_library: &BlockLibrary,
+    ambient_occlusion: bool,
+) -> Result<HashSet<Face>, TestCaseError> {
+    let mut mesher = Mesher::new();
+    mesher.ambient_occlusion = ambient_occlusion;
+
+    let (quads, offsets) = mesher.mesh(chunk, chunk_pos, block_library, None);
+    let mut faces = HashSet::new();
+
+    for transparent in [false, true] {
+        for signed_axis in SignedAxis::ALL {
+            let range = if transparent {
+                offsets.transparent_range(signed_axis)
+            } else {
+                offsets.range(signed_axis)
+            };
+
+            for quad in &quads[range.start as usize..range.end as usize] {
+                prop_assert_eq!(quad.signed_axis(), signed_axis);
+
+                let (w, h) = quad.size();
+                prop_assert!(w > 0 && h > 0, "empty quad {:?}", (w, h));
+
+                for voxel_pos in quad.covered() {
+                    let face = Face {
+                        signed_axis,
+                        transparent,
+                        voxel_pos,
+                        texture_index: quad.texture_index(),
+                    };
+                    prop_assert!(faces.insert(face), "overlapping {:?}", face);
+                }
+            }
+        }
+    }
+
+    prop_assert_eq!(offsets.transparent().end as usize, quads.len());
+
+    Ok(faces)
+}
+
+fn assert_same_faces(
+    spec: &ChunkSpec,
+    chunk_pos: IVec3,
+    ambient_occlusion: bool,
+) -> Result<(), TestCaseError> {
+    let block_library = library();
+    let chunk = spec.build(&block_library);
+
+    let expected = reference_faces(&chunk, chunk_pos, &block_library);
+    let actual = mesher_faces(&chunk, chunk_pos, &block_library, ambient_occlusion)?;
+
+    let mut gaps = expected.difference(&actual).collect::<Vec<_>>();
+    let mut extra = actual.difference(&expected).collect::<Vec<_>>();
+    gaps.truncate(8);
+    extra.truncate(8);
+
+    prop_assert!(
+        gaps.is_empty() && extra.is_empty(),
+        "missing {:?}, extra {:?}",
+        gaps,
+        extra
+    );
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn mesher_matches_reference(
+        spec in arb_chunk(),
+        chunk_pos in (-4..4, -4..4, -4..4).prop_map(|(x, y, z)| IVec3::new(x, y, z)),
+        ambient_occlusion in any::<bool>(),
+    ) {
+        assert_same_faces(&spec, chunk_pos, ambient_occlusion)?;
+    }
+
+    #[test]
+    fn quad_packing_round_trips(
+        pos in (-1000..1000, -1000..1000, -1000..1000).prop_map(|(x, y, z)| IVec3::new(x, y, z)),
+        texture_index in 0..=u16::MAX as u32,
+        w in 1..=pad::LEN as u32 - 2,
+        h in 1..=pad::LEN as u32 - 2,
+        signed_axis in proptest::sample::select(SignedAxis::ALL.to_vec()),
+        lighting in any::<u16>(),
+    ) {
+        let quad = VoxelQuad::new(pos, texture_index, w, h, signed_axis, lighting as u32);
+
+        prop_assert_eq!(quad.pos(), pos);
+        prop_assert_eq!(quad.texture_index(), texture_index);
+        prop_assert_eq!(quad.size(), (w, h));
+        prop_assert_eq!(quad.signed_axis(), signed_axis);
+        prop_assert_eq!(quad.lighting(), lighting as u32);
+        prop_assert_eq!(quad.covered().count(), (w * h) as usize);
+    }
+}
+
+#[test]
+fn empty_chunk_has_no_quads() {
+    let spec = ChunkSpec {
+        fill: None,
+        boxes: Vec::new(),
+        voxels: Vec::new(),
+    };
+    assert_same_faces(&spec, IVec3::ZERO, true).unwrap();
+}
+
+#[test]
+fn full_chunk_has_no_quads() {
+    let block_library = library();
+    let spec = ChunkSpec {
+        fill: Some(0),
+        boxes: Vec::new(),
+        voxels: Vec::new(),
+    };
+
+    let faces = mesher_faces(
+        &spec.build(&block_library),
+        IVec3::ZERO,
+        &block_library,
+        true,
+    );
+    assert!(faces.unwrap().is_empty());
+}
+
+#[test]
+fn uniform_chunks_store_no_masks() {
+    let block_library = library();
+    let mut chunk = Chunk::from_voxels(Voxels::Uniform(VoxelIndex::new(0)), &block_library);
+    assert_eq!(chunk.heap_size(), 0);
+
+    // a hole needs voxels and an opaque mask, it shows a face on every side
+    chunk.set(UVec3::splat(10), None, &block_library);
+    assert!(chunk.heap_size() > 0);
+
+    let faces = mesher_faces(&chunk, IVec3::ZERO, &block_library, true);
+    assert_eq!(faces.unwrap().len(), 6);
+
+    chunk.set(UVec3::splat(10), VoxelIndex::new(0), &block_library);
+    chunk.compact();
+    assert_eq!(chunk.heap_size(), 0);
+}
+
+#[test]
+fn single_voxel_has_six_quads() {
+    let block_library = library();
+    let spec = ChunkSpec {
+        fill: None,
+        boxes: Vec::new(),
+        voxels: vec![(UVec3::splat(10), Some(4))],
+    };
+
+    let faces = mesher_faces(
+        &spec.build(&block_library),
+        IVec3::ZERO,
+        &block_library,
+        true,
+    );
+    assert_eq!(faces.unwrap().len(), 6);
+    assert_same_faces(&spec, IVec3::ZERO, true).unwrap();
+}
+
+#[test]
+fn slab_merges_into_one_quad_per_face() {
+    let block_library = library();
+    let spec = ChunkSpec {
+        fill: None,
+        boxes: vec![(UVec3::ONE, UVec3::new(62, 1, 62), Some(0))],
+        voxels: Vec::new(),
+    };
+
+    let mut mesher = Mesher::new();
+    mesher.ambient_occlusion = false;
+    let (_, offsets) = mesher.mesh(
+        &spec.build(&block_library),
+        IVec3::ZERO,
+        &block_library,
+        None,
+    );
+
+    assert_eq!(offsets.range(PosY).len(), 1);
+    assert_eq!(offsets.range(NegY).len(), 1);
+    assert_same_faces(&spec, IVec3::ZERO, false).unwrap();
+}
+
+#[test]
+fn slab_does_not_cull_neighbours() {
+    let spec = ChunkSpec {
+        fill: None,
+        boxes: Vec::new(),
+        voxels: vec![
+            (UVec3::splat(10), Some(0)),
+            (UVec3::new(10, 11, 10), Some(SLAB)),
+        ],
+    };
+
+    let block_library = library();
+    let faces = mesher_faces(
+        &spec.build(&block_library),
+        IVec3::ZERO,
+        &block_library,
+        true,
+    );
+    assert_eq!(faces.unwrap().len(), 6);
+    assert_same_faces(&spec, IVec3::ZERO, true).unwrap();
+}
+
+#[test]
+fn slab_is_culled_by_opaque_neighbours() {
+    let block_library = library();
+    let mut model_mesher = ModelMesher::new();
+
+    let alone = ChunkSpec {
+        fill: None,
+        boxes: Vec::new(),
+        voxels: vec![(UVec3::splat(10), Some(SLAB))],
+    };
+    let (quads, offsets) = model_mesher.mesh(
+        &alone.build(&block_library),
+        IVec3::ZERO,
+        &block_library,
+        None,
+    );
+    assert_eq!(quads.len(), 6);
+    assert_eq!(offsets.opaque().len(), 6);
+
+    // only the bottom lies on a side of the voxel, the top is halfway up
+    let on_stone = ChunkSpec {
+        fill: None,
+        boxes: Vec::new(),
+        voxels: vec![
+            (UVec3::new(10, 9, 10), Some(0)),
+            (UVec3::splat(10), Some(SLAB)),
+            (UVec3::new(10, 11, 10), Some(0)),
+        ],
+    };
+    model_mesher.clear();
+    let (quads, _) = model_mesher.mesh(
+        &on_stone.build(&block_library),
+        IVec3::ZERO,
+        &block_library,
+        None,
+    );
+    assert_eq!(quads.len(), 5);
+}
@@ -0,0 +1,176 @@
+use voxel::chunk::{PalettedVoxels, VoxelIndex, Voxels, pad::VOL};
+
+// Index widths are powers of two, so a palette of `n` entries is stored in
+// the smallest of 1, 2, 4, 8 or 16 bits that fits `n`.
+
+fn voxel(index: usize) -> Option<VoxelIndex> {
+    VoxelIndex::new(index)
+}
+
+fn expected_bits(entries: usize) -> u32 {
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .find(|bits| entries <= 1 << bits)
+        .unwrap()
+}
+
+fn paletted(voxels: &Voxels) -> &PalettedVoxels {
+    match voxels {
+        Voxels::Paletted(paletted) => paletted,
+        Voxels::Uniform(_) => panic!("expected paletted voxels"),
+    }
+}
+
+/// `count` values repeating over the whole chunk.
+fn pattern(count: usize) -> impl Fn(usize) -> Option<VoxelIndex> {
+    move |index| voxel(index * 7 % count)
+}
+
+#[test]
+fn grows_through_every_width() {
+    let mut voxels = Voxels::EMPTY;
+
+    for value in 0..300 {
+        voxels.set(value * 31, voxel(value));
+
+        // `None` is still in the palette
+        let paletted = paletted(&voxels);
+        assert_eq!(paletted.palette().len(), value + 2);
+        assert_eq!(paletted.bits(), expected_bits(value + 2));
+    }
+
+    for index in 0..VOL {
+        let expected = if index % 31 == 0 && index / 31 < 300 {
+            voxel(index / 31)
+        } else {
+            None
+        };
+        assert_eq!(voxels.get(index), expected, "voxel {index}");
+    }
+}
+
+#[test]
+fn values_round_trip_at_every_width() {
+    for count in [2, 3, 4, 5, 16, 17, 256, 257, 1000] {
+        let value = pattern(count);
+
+        let mut voxels = Voxels::EMPTY;
+        for index in 0..VOL {
+            voxels.set(index, value(index));
+        }
+
+        // the unused `None` is dropped
+        voxels.compact();
+        assert_eq!(paletted(&voxels).palette().len(), count);
+        assert_eq!(
+            paletted(&voxels).bits(),
+            expected_bits(count),
+            "{count} values"
+        );
+
+        for index in 0..VOL {
+            assert_eq!(
+                voxels.get(index),
+                value(index),
+                "voxel {index} of {count} values"
+            );
+        }
+    }
+}
+
+#[test]
+fn compact_shrinks_to_the_values_in_use() {
+    let mut voxels = Voxels::EMPTY;
+    for index in 0..VOL {
+        voxels.set(index, pattern(300)(index));
+    }
+    assert_eq!(paletted(&voxels).bits(), 16);
+
+    for index in 0..VOL {
+        if voxels.get(index) != voxel(0) {
+            voxels.set(index, voxel(1));
+        }
+    }
+
+    voxels.compact();
+    assert_eq!(paletted(&voxels).bits(), 1);
+
+    for index in 0..VOL {
+        assert_eq!(voxels.get(index), pattern(300)(index).min(voxel(1)));
+    }
+}
+
+#[test]
+fn compact_falls_back_to_uniform() {
+    let mut voxels = Voxels::EMPTY;
+    for index in 0..VOL {
+        voxels.set(index, pattern(20)(index));
+    }
+
+    for index in 0..VOL {
+        voxels.set(index, voxel(5));
+    }
+
+    voxels.compact();
+    assert_eq!(voxels.uniform(), Some(voxel(5)));
+    assert_eq!(voxels.heap_size(), 0);
+}
+
+#[test]
+fn setting_the_uniform_value_stays_uniform() {
+    let mut voxels = Voxels::Uniform(voxel(3));
+    voxels.set(0, voxel(3));
+    assert_eq!(voxels.uniform(), Some(voxel(3)));
+
+    voxels.set(0, voxel(4));
+    assert_eq!(paletted(&voxels).bits(), 1);
+    assert_eq!(voxels.get(0), voxel(4));
+    assert_eq!(voxels.get(1), voxel(3));
+}
+
+#[test]
+fn from_parts_round_trips() {
+    for count in [2, 4, 16, 256, 1000] {
+        let mut voxels = Voxels::EMPTY;
+        for index in 0..VOL {
+            voxels.set(index, pattern(count)(index));
+        }
+        voxels.compact();
+
+        let paletted = paletted(&voxels);
+        let rebuilt = PalettedVoxels::from_parts(
+            paletted.palette().to_vec(),
+            paletted.bits(),
+            paletted.words().into(),
+        )
+        .unwrap();
+
+        assert_eq!(rebuilt.bits(), paletted.bits());
+        for index in 0..VOL {
+            assert_eq!(rebuilt.get(index), paletted.get(index));
+        }
+    }
+}
+
+#[test]
+fn from_parts_rejects_inconsistent_parts() {
+    let words = |bits: usize| vec![0; VOL * bits / 64].into_boxed_slice();
+
+    // not a power of two
+    assert!(PalettedVoxels::from_parts(vec![None], 3, words(3)).is_none());
+    // wrong number of words
+    assert!(PalettedVoxels::from_parts(vec![None], 2, words(1)).is_none());
+    // empty palette
+    assert!(PalettedVoxels::from_parts(Vec::new(), 1, words(1)).is_none());
+    // more entries than the width holds
+    assert!(PalettedVoxels::from_parts(vec![None; 3], 1, words(1)).is_none());
+    // an index past the palette
+    assert!(
+        PalettedVoxels::from_parts(
+            vec![None, voxel(0), voxel(1)],
+            2,
+            vec![u64::MAX; VOL * 2 / 64].into()
+        )
+        .is_none()
+    );
+}
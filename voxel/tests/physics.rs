@@ -0,0 +1,182 @@
+mod common;
+
+use bevy::math::{IVec3, Vec3, Vec3A, bounding::Aabb3d};
+use voxel::{block_lib::BlockLibrary, chunk::ChunkMap};
+
+use common::{around_origin, block, block_library, world};
+
+// Chunks `-1..=1` on every axis are loaded, the chunk past the origin on
+// `x` starts at voxel `63`. Bodies are the size of a player.
+
+const HALF_SIZE: Vec3 = Vec3::new(0.3, 0.9, 0.3);
+const SLIDES: usize = 3;
+
+fn library() -> BlockLibrary {
+    let mut stone = block();
+    stone.collision_aabbs = vec![Aabb3d {
+        min: Vec3A::ZERO,
+        max: Vec3A::ONE,
+    }];
+
+    block_library([("test:stone", stone)])
+}
+
+/// Stone wherever `solid` returns `true`.
+fn solid_world(block_library: &BlockLibrary, solid: impl Fn(IVec3) -> bool) -> ChunkMap {
+    world(block_library, around_origin(), |voxel_pos| {
+        solid(voxel_pos).then_some("test:stone")
+    })
+}
+
+/// A body standing `gap` above `y = 11`, the top of a floor.
+fn body(x: f32, z: f32, gap: f32) -> Aabb3d {
+    Aabb3d::new(Vec3::new(x, 11.0 + HALF_SIZE.y + gap, z), HALF_SIZE)
+}
+
+fn assert_near(actual: Vec3, expected: Vec3) {
+    assert!(
+        actual.abs_diff_eq(expected, 1e-2),
+        "{actual} is not near {expected}"
+    );
+}
+
+#[test]
+fn lands_on_the_floor() {
+    let block_library = library();
+    let chunk_map = solid_world(&block_library, |voxel_pos| voxel_pos.y <= 10);
+
+    let aabb = body(5.5, 5.5, 1.0);
+    let motion = Vec3::new(0.0, -5.0, 0.0);
+
+    let hit = chunk_map.sweep(&block_library, aabb, motion).unwrap();
+    assert!((hit.time - 0.2).abs() < 1e-5, "{}", hit.time);
+    assert_eq!(hit.normal, Vec3::Y);
+    assert_eq!(hit.voxel_pos.y, 10);
+
+    let (moved, normals) = chunk_map.move_and_slide(&block_library, aabb, motion, SLIDES);
+    assert_near(moved, Vec3::new(0.0, -1.0, 0.0));
+    assert_eq!(normals, vec![Vec3::Y]);
+
+    // resting on the floor without sinking into it
+    let landed = Aabb3d::new(Vec3::from(aabb.center()) + moved, HALF_SIZE);
+    assert!(landed.min.y >= 11.0);
+    assert!(!chunk_map.overlaps(&block_library, landed));
+}
+
+#[test]
+fn falling_diagonally_keeps_moving_along_the_floor() {
+    let block_library = library();
+    let chunk_map = solid_world(&block_library, |voxel_pos| voxel_pos.y <= 10);
+
+    let aabb = body(5.5, 5.5, 1.0);
+    let (moved, normals) =
+        chunk_map.move_and_slide(&block_library, aabb, Vec3::new(2.0, -2.0, 0.0), SLIDES);
+
+    // the motion into the floor is dropped, the rest carries on
+    assert_near(moved, Vec3::new(2.0, -1.0, 0.0));
+    assert_eq!(normals, vec![Vec3::Y]);
+}
+
+#[test]
+fn slides_along_a_wall() {
+    let block_library = library();
+    let chunk_map = solid_world(&block_library, |voxel_pos| {
+        voxel_pos.y <= 10 || voxel_pos.x >= 20
+    });
+
+    // 0.7 from the wall, just above the floor
+    let aabb = body(19.0, 5.5, 0.1);
+    let (moved, normals) =
+        chunk_map.move_and_slide(&block_library, aabb, Vec3::new(2.0, 0.0, 3.0), SLIDES);
+
+    assert_near(moved, Vec3::new(0.7, 0.0, 3.0));
+    assert_eq!(normals, vec![Vec3::NEG_X]);
+}
+
+#[test]
+fn stops_in_a_corner() {
+    let block_library = library();
+    let chunk_map = solid_world(&block_library, |voxel_pos| {
+        voxel_pos.y <= 10 || voxel_pos.x >= 20 || voxel_pos.z >= 20
+    });
+
+    let aabb = body(19.0, 19.0, 0.1);
+    let (moved, normals) =
+        chunk_map.move_and_slide(&block_library, aabb, Vec3::new(2.0, 0.0, 3.0), SLIDES);
+
+    assert_near(moved, Vec3::new(0.7, 0.0, 0.7));
+    assert_eq!(normals.len(), 2);
+    assert!(normals.contains(&Vec3::NEG_X) && normals.contains(&Vec3::NEG_Z));
+}
+
+#[test]
+fn sweep_crosses_chunk_borders() {
+    let block_library = library();
+    let chunk_map = solid_world(&block_library, |voxel_pos| {
+        voxel_pos == IVec3::new(70, 12, 5)
+    });
+
+    // from the chunk at the origin into the one past it
+    let aabb = body(55.0, 5.5, 0.1);
+    let hit = chunk_map
+        .sweep(&block_library, aabb, Vec3::new(20.0, 0.0, 0.0))
+        .unwrap();
+
+    assert!((hit.time - 14.7 / 20.0).abs() < 1e-5, "{}", hit.time);
+    assert_eq!(hit.normal, Vec3::NEG_X);
+    assert_eq!(hit.voxel_pos, IVec3::new(70, 12, 5));
+}
+
+#[test]
+fn lands_across_a_chunk_border() {
+    let block_library = library();
+
+    // only the chunk past the origin has a floor
+    let chunk_map = solid_world(&block_library, |voxel_pos| {
+        voxel_pos.y <= 10 && voxel_pos.x >= 63
+    });
+
+    // straddling the border, over the floor by 0.2
+    let aabb = body(63.1, 5.5, 1.0);
+    let (moved, normals) =
+        chunk_map.move_and_slide(&block_library, aabb, Vec3::new(0.0, -5.0, 0.0), SLIDES);
+
+    assert_near(moved, Vec3::new(0.0, -1.0, 0.0));
+    assert_eq!(normals, vec![Vec3::Y]);
+
+    // clear of the floor, it falls through
+    let aabb = body(62.5, 5.5, 1.0);
+    let (_, normals) =
+        chunk_map.move_and_slide(&block_library, aabb, Vec3::new(0.0, -5.0, 0.0), SLIDES);
+    assert!(normals.is_empty());
+}
+
+#[test]
+fn moves_out_of_overlapping_colliders() {
+    let block_library = library();
+    let chunk_map = solid_world(&block_library, |voxel_pos| voxel_pos.y <= 10);
+
+    // sunk half a voxel into the floor
+    let aabb = body(5.5, 5.5, -0.5);
+    assert!(chunk_map.overlaps(&block_library, aabb));
+    assert!(
+        chunk_map
+            .sweep(&block_library, aabb, Vec3::new(0.0, 2.0, 0.0))
+            .is_none()
+    );
+}
+
+#[test]
+fn unloaded_chunks_have_no_colliders() {
+    let block_library = library();
+    let chunk_map = solid_world(&block_library, |_| true);
+
+    // past the loaded chunks on `x`
+    let aabb = Aabb3d::new(Vec3::new(200.0, 5.0, 5.0), HALF_SIZE);
+    assert!(!chunk_map.overlaps(&block_library, aabb));
+    assert!(
+        chunk_map
+            .sweep(&block_library, aabb, Vec3::new(0.0, -10.0, 0.0))
+            .is_none()
+    );
+}
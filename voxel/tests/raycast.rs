@@ -0,0 +1,194 @@
+mod common;
+
+use bevy::math::{IVec3, Vec3};
+use voxel::{
+    block_lib::BlockLibrary,
+    chunk::{ChunkMap, VoxelIndex},
+    math::signed_axis::*,
+    raycast::opaque,
+};
+
+use common::{around_origin, block, block_library, world};
+
+// Chunks `-1..=1` on every axis are loaded, from voxel `-61` to `124`.
+
+fn library() -> BlockLibrary {
+    let mut glass = block();
+    glass.is_transparent = true;
+    glass.light_opacity = 0;
+
+    block_library([("test:stone", block()), ("test:glass", glass)])
+}
+
+fn stone(block_library: &BlockLibrary) -> Option<VoxelIndex> {
+    block_library.lookup("test:stone")
+}
+
+/// Stone at and below `y = 10`.
+fn floor(block_library: &BlockLibrary) -> ChunkMap {
+    world(block_library, around_origin(), |voxel_pos| {
+        (voxel_pos.y <= 10).then_some("test:stone")
+    })
+}
+
+fn any(_: VoxelIndex) -> bool {
+    true
+}
+
+#[test]
+fn hits_the_floor() {
+    let block_library = library();
+    let chunk_map = floor(&block_library);
+
+    let hit = chunk_map
+        .raycast(Vec3::new(5.5, 20.5, 5.5), Vec3::NEG_Y, 100.0, any)
+        .unwrap();
+
+    assert_eq!(hit.voxel_pos, IVec3::new(5, 10, 5));
+    assert_eq!(Some(hit.voxel), stone(&block_library));
+    assert_eq!(hit.face, PosY);
+    assert_eq!(hit.distance, 9.5);
+    assert_eq!(hit.adjacent_pos(), IVec3::new(5, 11, 5));
+}
+
+#[test]
+fn misses_past_max_distance() {
+    let block_library = library();
+    let chunk_map = floor(&block_library);
+
+    let origin = Vec3::new(5.5, 20.5, 5.5);
+    assert!(chunk_map.raycast(origin, Vec3::NEG_Y, 9.0, any).is_none());
+    assert!(chunk_map.raycast(origin, Vec3::Y, 50.0, any).is_none());
+    assert!(chunk_map.raycast(origin, Vec3::ZERO, 50.0, any).is_none());
+}
+
+#[test]
+fn hits_the_face_toward_the_ray() {
+    let block_library = library();
+    let target = IVec3::new(20, 20, 20);
+    let chunk_map = world(&block_library, around_origin(), |voxel_pos| {
+        (voxel_pos == target).then_some("test:stone")
+    });
+
+    for signed_axis in SignedAxis::ALL {
+        let normal = IVec3::from_array(signed_axis.coords());
+        let origin = (target + normal * 5).as_vec3() + 0.5;
+
+        let hit = chunk_map
+            .raycast(origin, -normal.as_vec3(), 10.0, any)
+            .unwrap();
+
+        assert_eq!(hit.voxel_pos, target);
+        assert_eq!(hit.face, signed_axis);
+        assert_eq!(hit.distance, 4.5);
+        assert_eq!(hit.adjacent_pos(), target + normal);
+    }
+}
+
+#[test]
+fn crosses_chunk_borders() {
+    let block_library = library();
+
+    // in the chunk past `x = 62`, and diagonally two chunks away
+    let targets = [IVec3::new(70, 5, 5), IVec3::new(-40, -40, -40)];
+    let chunk_map = world(&block_library, around_origin(), |voxel_pos| {
+        targets.contains(&voxel_pos).then_some("test:stone")
+    });
+
+    let hit = chunk_map
+        .raycast(Vec3::new(2.5, 5.5, 5.5), Vec3::X, 100.0, any)
+        .unwrap();
+    assert_eq!(hit.voxel_pos, targets[0]);
+    assert_eq!(hit.face, NegX);
+    assert_eq!(hit.distance, 67.5);
+
+    let hit = chunk_map
+        .raycast(Vec3::splat(10.5), Vec3::NEG_ONE, 100.0, any)
+        .unwrap();
+    assert_eq!(hit.voxel_pos, targets[1]);
+}
+
+#[test]
+fn stops_at_unloaded_chunks() {
+    let block_library = library();
+    let chunk_map = world(&block_library, around_origin(), |_| None);
+
+    // a block past the loaded chunks can't be seen
+    let origin = Vec3::new(0.5, 0.5, 0.5);
+    for direction in [Vec3::X, Vec3::NEG_Y, Vec3::new(1.0, 2.0, -3.0)] {
+        assert!(
+            chunk_map
+                .raycast(origin, direction, f32::INFINITY, any)
+                .is_none()
+        );
+    }
+
+    // starting outside of them
+    let outside = Vec3::new(500.5, 0.5, 0.5);
+    assert!(
+        chunk_map
+            .raycast(outside, Vec3::NEG_X, f32::INFINITY, any)
+            .is_none()
+    );
+}
+
+#[test]
+fn rejects_non_finite_input() {
+    let block_library = library();
+    let chunk_map = floor(&block_library);
+
+    let origin = Vec3::new(5.5, 20.5, 5.5);
+    assert!(
+        chunk_map
+            .raycast(origin, Vec3::NEG_Y, f32::NAN, any)
+            .is_none()
+    );
+    assert!(
+        chunk_map
+            .raycast(Vec3::NAN, Vec3::NEG_Y, 100.0, any)
+            .is_none()
+    );
+    assert!(
+        chunk_map
+            .raycast(origin, Vec3::new(0.0, f32::NAN, 0.0), 100.0, any)
+            .is_none()
+    );
+}
+
+#[test]
+fn filter_skips_blocks() {
+    let block_library = library();
+    let chunk_map = world(
+        &block_library,
+        around_origin(),
+        |voxel_pos| match voxel_pos.y {
+            ..=10 => Some("test:stone"),
+            11 => Some("test:glass"),
+            _ => None,
+        },
+    );
+
+    let origin = Vec3::new(5.5, 20.5, 5.5);
+
+    let hit = chunk_map.raycast(origin, Vec3::NEG_Y, 100.0, any).unwrap();
+    assert_eq!(hit.voxel_pos.y, 11);
+
+    let hit = chunk_map
+        .raycast(origin, Vec3::NEG_Y, 100.0, opaque(&block_library))
+        .unwrap();
+    assert_eq!(hit.voxel_pos.y, 10);
+    assert_eq!(hit.face, PosY);
+}
+
+#[test]
+fn filter_can_lock_chunks() {
+    let block_library = library();
+    let chunk_map = floor(&block_library);
+
+    // would deadlock if the chunk of the voxel were still locked
+    let hit = chunk_map.raycast(Vec3::new(5.5, 20.5, 5.5), Vec3::NEG_Y, 100.0, |_| {
+        chunk_map.get_mut(&IVec3::ZERO).is_some()
+    });
+
+    assert_eq!(hit.unwrap().voxel_pos, IVec3::new(5, 10, 5));
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use bevy::{
+    math::{IVec3, UVec3},
+    platform::collections::HashMap,
+};
+use voxel::{
+    block_lib::{BlockLibrary, remap::BlockRemap},
+    chunk::{
+        ChunkMap,
+        generator::{
+            PendingWrites,
+            decoration::{PendingWrite, Placement},
+            from_fn,
+        },
+    },
+    history::{EditHistory, VoxelChange},
+};
+
+use common::{block, block_library};
+
+fn libraries() -> (BlockLibrary, BlockLibrary) {
+    let old = block_library([("test:stone", block()), ("test:dirt", block())]);
+    let new = block_library([
+        ("test:grass", block()),
+        ("test:dirt", block()),
+        ("test:stone", block()),
+    ]);
+    (old, new)
+}
+
+#[test]
+fn blocks_keep_their_names() {
+    let (old, new) = libraries();
+    let remap = BlockRemap::between(&old, &new);
+
+    assert!(!remap.is_identity());
+    for name in ["test:stone", "test:dirt"] {
+        assert_eq!(remap.get(old.lookup(name)), new.lookup(name), "{name}");
+    }
+    assert_eq!(remap.get(None), None);
+}
+
+#[test]
+fn removed_blocks_become_empty() {
+    let (old, _) = libraries();
+    let new = block_library([("test:dirt", block())]);
+    let remap = BlockRemap::between(&old, &new);
+
+    assert_eq!(remap.get(old.lookup("test:stone")), None);
+    assert_eq!(remap.get(old.lookup("test:dirt")), new.lookup("test:dirt"));
+}
+
+#[test]
+fn same_order_is_identity() {
+    let (old, _) = libraries();
+    let new = block_library([("test:stone", block()), ("test:dirt", block())]);
+
+    assert!(BlockRemap::between(&old, &new).is_identity());
+}
+
+#[test]
+fn chunks_are_remapped() {
+    let (old, new) = libraries();
+    let remap = BlockRemap::between(&old, &new);
+    let stone = old.lookup("test:stone");
+
+    let chunk_map = ChunkMap::default();
+    chunk_map.insert(IVec3::ZERO, from_fn(IVec3::ZERO, &old, |_| stone));
+    chunk_map.remap(&remap, &new);
+
+    let voxel_opt = chunk_map.get_voxel(IVec3::new(5, 5, 5)).unwrap();
+    assert_eq!(voxel_opt, new.lookup("test:stone"));
+}
+
+#[test]
+fn history_is_remapped() {
+    let (old, new) = libraries();
+    let remap = BlockRemap::between(&old, &new);
+
+    let mut history = EditHistory::default();
+    history.record(vec![VoxelChange::new(
+        IVec3::ZERO,
+        UVec3::new(5, 5, 5),
+        old.lookup("test:stone"),
+        old.lookup("test:dirt"),
+    )]);
+    history.remap(&remap);
+
+    let change = history.undo().unwrap().changes[0];
+    assert_eq!(change.old, new.lookup("test:stone"));
+    assert_eq!(change.new, new.lookup("test:dirt"));
+}
+
+#[test]
+fn pending_writes_are_remapped() {
+    let (old, new) = libraries();
+    let remap = BlockRemap::between(&old, &new);
+
+    let pending = PendingWrites::default();
+    let write = PendingWrite {
+        pos: UVec3::new(1, 5, 5),
+        voxel_opt: old.lookup("test:stone"),
+        placement: Placement::Replace,
+    };
+    pending.spill(IVec3::ZERO, HashMap::from_iter([(IVec3::X, vec![write])]));
+    pending.remap(&remap);
+
+    let writes = pending.writes(IVec3::ZERO, IVec3::X);
+    assert_eq!(writes[0].voxel_opt, new.lookup("test:stone"));
+}
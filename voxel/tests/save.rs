@@ -0,0 +1,363 @@
+mod common;
+
+use bevy::math::IVec3;
+use voxel::{
+    block_lib::BlockLibrary,
+    chunk::{Chunk, VoxelIndex, Voxels, pad::VOL},
+    save::{
+        ChunkStorage,
+        block_table::BlockTable,
+        chunk_format,
+        region::{self, RegionFile, local_index},
+    },
+};
+
+use common::{TempDir, assert_same_voxels, block, block_library, chunk_from_fn};
+
+// Sectors are 4096 bytes and the header of a region takes the first 9.
+
+const SECTOR: usize = 4096;
+const HEADER_SECTORS: usize = 9;
+
+fn library() -> BlockLibrary {
+    block_library([
+        ("test:stone", block()),
+        ("test:dirt", block()),
+        ("test:grass", block()),
+    ])
+}
+
+fn voxel(block_library: &BlockLibrary, name: &str) -> Option<VoxelIndex> {
+    block_library.lookup(name)
+}
+
+/// Stone below `height`, air above, with a dirt column on top.
+fn terrain(block_library: &BlockLibrary, height: usize) -> Chunk {
+    let stone = voxel(block_library, "test:stone");
+    let dirt = voxel(block_library, "test:dirt");
+
+    chunk_from_fn(block_library, |index| {
+        let (x, y, z) = (index % 64, index / 64 % 64, index / 4096);
+        match y {
+            _ if y < height => stone,
+            _ if y == height && x == z => dirt,
+            _ => None,
+        }
+    })
+}
+
+#[test]
+fn chunks_round_trip_through_storage() {
+    let dir = TempDir::new("round-trip");
+    let block_library = library();
+
+    let chunks = [
+        (IVec3::new(0, 0, 0), terrain(&block_library, 20)),
+        (IVec3::new(-1, 2, 5), terrain(&block_library, 50)),
+        (
+            IVec3::new(3, -4, 0),
+            Chunk::from_voxels(
+                Voxels::Uniform(voxel(&block_library, "test:grass")),
+                &block_library,
+            ),
+        ),
+        (IVec3::new(0, 1, 0), Chunk::EMPTY),
+    ];
+
+    let storage = ChunkStorage::new(&*dir).unwrap();
+    for (chunk_pos, chunk) in &chunks {
+        storage
+            .save_chunk(*chunk_pos, chunk, &block_library)
+            .unwrap();
+    }
+
+    for (chunk_pos, chunk) in &chunks {
+        let loaded = storage.load_chunk(*chunk_pos, &block_library).unwrap();
+        assert_same_voxels(&loaded.unwrap(), chunk);
+    }
+
+    assert!(
+        storage
+            .load_chunk(IVec3::new(9, 9, 9), &block_library)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn chunks_survive_reopening_storage() {
+    let dir = TempDir::new("reopen");
+    let block_library = library();
+    let chunk = terrain(&block_library, 30);
+
+    let storage = ChunkStorage::new(&*dir).unwrap();
+    storage
+        .save_chunk(IVec3::ONE, &chunk, &block_library)
+        .unwrap();
+    storage.close().unwrap();
+    drop(storage);
+
+    // blocks are found by name, not by their place in the library
+    let reordered = block_library([
+        ("test:grass", block()),
+        ("test:dirt", block()),
+        ("test:stone", block()),
+    ]);
+
+    let storage = ChunkStorage::new(&*dir).unwrap();
+    assert!(storage.contains_chunk(IVec3::ONE).unwrap());
+    assert!(!storage.contains_chunk(IVec3::ZERO).unwrap());
+
+    let loaded = storage.load_chunk(IVec3::ONE, &reordered).unwrap().unwrap();
+    for index in 0..VOL {
+        let expected = chunk
+            .voxels()
+            .get(index)
+            .map(|voxel| block_library.identifier_string(voxel));
+        let actual = loaded
+            .voxels()
+            .get(index)
+            .map(|voxel| reordered.identifier_string(voxel));
+        assert_eq!(actual, expected, "voxel {index}");
+    }
+}
+
+#[test]
+fn region_holds_many_chunks() {
+    let dir = TempDir::new("region");
+    let block_library = library();
+    let storage = ChunkStorage::new(&*dir).unwrap();
+
+    // every chunk in one region, each with its own height
+    let chunk_positions = [
+        IVec3::new(0, 0, 0),
+        IVec3::new(1, 0, 0),
+        IVec3::new(0, 1, 0),
+        IVec3::new(0, 0, 1),
+        IVec3::new(7, 3, 12),
+        IVec3::splat(region::LEN - 1),
+    ];
+
+    for (height, chunk_pos) in chunk_positions.iter().enumerate() {
+        let chunk = terrain(&block_library, height * 5 + 1);
+        storage
+            .save_chunk(*chunk_pos, &chunk, &block_library)
+            .unwrap();
+    }
+    storage.close().unwrap();
+
+    let regions = std::fs::read_dir(&*dir)
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .path()
+                .extension()
+                .is_some_and(|e| e == "bin")
+        })
+        .count();
+    assert_eq!(regions, 1);
+
+    for (height, chunk_pos) in chunk_positions.iter().enumerate() {
+        let loaded = storage
+            .load_chunk(*chunk_pos, &block_library)
+            .unwrap()
+            .unwrap();
+        assert_same_voxels(&loaded, &terrain(&block_library, height * 5 + 1));
+    }
+
+    storage.remove_chunk(chunk_positions[4]).unwrap();
+    assert!(!storage.contains_chunk(chunk_positions[4]).unwrap());
+    assert!(storage.contains_chunk(chunk_positions[5]).unwrap());
+}
+
+#[test]
+fn local_indices_are_unique_within_a_region() {
+    let mut seen = vec![false; region::VOL];
+
+    for z in 0..region::LEN {
+        for y in 0..region::LEN {
+            for x in 0..region::LEN {
+                let chunk_pos = IVec3::new(x, y, z) - region::LEN;
+                assert_eq!(region::region_pos(chunk_pos), IVec3::splat(-1));
+
+                let index = local_index(chunk_pos);
+                assert!(!seen[index], "{chunk_pos} shares index {index}");
+                seen[index] = true;
+            }
+        }
+    }
+}
+
+fn file_len(path: &std::path::Path) -> usize {
+    std::fs::metadata(path).unwrap().len() as usize
+}
+
+#[test]
+fn region_reuses_freed_sectors() {
+    let dir = TempDir::new("sectors");
+    let path = dir.join("region.bin");
+    let mut region = RegionFile::open(&path).unwrap();
+    assert_eq!(file_len(&path), HEADER_SECTORS * SECTOR);
+
+    // three sectors, then one
+    region.write(0, &[1; 3 * SECTOR - 10]).unwrap();
+    region.write(1, &[2; 100]).unwrap();
+    let len = file_len(&path);
+    assert_eq!(len, (HEADER_SECTORS + 3) * SECTOR + 100);
+
+    // the new copy goes after the old one, which is then freed
+    region.write(0, &[3; 50]).unwrap();
+    let len_after_move = file_len(&path);
+    assert_eq!(len_after_move, (HEADER_SECTORS + 4) * SECTOR + 50);
+
+    // fits in the three freed sectors
+    region.write(2, &[4; 2 * SECTOR]).unwrap();
+    region.write(3, &[5; 10]).unwrap();
+    assert_eq!(file_len(&path), len_after_move);
+
+    // the sector freed by the removal is too small for two
+    region.remove(1).unwrap();
+    region.write(4, &[6; SECTOR + 1]).unwrap();
+    assert_eq!(file_len(&path), (HEADER_SECTORS + 6) * SECTOR + 1);
+
+    assert_eq!(region.read(0).unwrap().unwrap(), vec![3; 50]);
+    assert_eq!(region.read(1).unwrap(), None);
+    assert_eq!(region.read(2).unwrap().unwrap(), vec![4; 2 * SECTOR]);
+    assert_eq!(region.read(3).unwrap().unwrap(), vec![5; 10]);
+    assert_eq!(region.read(4).unwrap().unwrap(), vec![6; SECTOR + 1]);
+}
+
+#[test]
+fn offset_table_survives_reopening() {
+    let dir = TempDir::new("offsets");
+    let path = dir.join("region.bin");
+
+    let entries = [
+        (0, 10),
+        (1, SECTOR * 2),
+        (region::VOL - 1, 7),
+        (300, SECTOR),
+    ];
+
+    {
+        let mut region = RegionFile::open(&path).unwrap();
+        for (index, len) in entries {
+            region.write(index, &vec![index as u8; len]).unwrap();
+        }
+        // leaves a gap of two sectors
+        region.remove(1).unwrap();
+        region.sync().unwrap();
+    }
+
+    let mut region = RegionFile::open(&path).unwrap();
+    assert!(!region.contains(2));
+
+    // the gap is found again, without overwriting the other entries
+    let len = file_len(&path);
+    region.write(2, &[9; 20]).unwrap();
+    assert_eq!(file_len(&path), len);
+
+    for (index, len) in entries {
+        if index == 1 {
+            assert!(!region.contains(index));
+            assert_eq!(region.read(index).unwrap(), None);
+        } else {
+            assert!(region.contains(index));
+            assert_eq!(region.read(index).unwrap().unwrap(), vec![index as u8; len]);
+        }
+    }
+    assert_eq!(region.read(2).unwrap().unwrap(), vec![9; 20]);
+}
+
+#[test]
+fn region_rejects_other_files() {
+    let dir = TempDir::new("magic");
+    let path = dir.join("region.bin");
+    std::fs::write(&path, vec![0; HEADER_SECTORS * SECTOR]).unwrap();
+
+    assert!(RegionFile::open(&path).is_err());
+}
+
+/// A version 1 chunk, naming its palette entries.
+fn named_chunk(palette: &[&str], bits: u8, words: &[u64]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"VXCK");
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.push(1);
+
+    bytes.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+    for name in palette {
+        bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+    }
+
+    bytes.push(bits);
+    for word in words {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    bytes
+}
+
+#[test]
+fn reads_version_1_chunks() {
+    let block_library = library();
+    let stone = voxel(&block_library, "test:stone");
+    let grass = voxel(&block_library, "test:grass");
+
+    // 2 bit indices, `0b10_01_00_11` repeated from the low bits: unknown,
+    // air, stone, grass
+    let words = vec![0x9393_9393_9393_9393; VOL * 2 / 64];
+    let bytes = named_chunk(&["", "test:stone", "test:grass", "test:missing"], 2, &words);
+
+    let chunk = chunk_format::decode(
+        &mut bytes.as_slice(),
+        &block_library,
+        &BlockTable::default(),
+    )
+    .unwrap();
+
+    for index in 0..VOL {
+        let expected = match index % 4 {
+            0 => None,
+            1 => None,
+            2 => stone,
+            _ => grass,
+        };
+        assert_eq!(chunk.voxels().get(index), expected, "voxel {index}");
+    }
+}
+
+#[test]
+fn version_1_chunks_are_saved_as_version_2() {
+    let block_library = library();
+    let words = vec![0xAAAA_AAAA_AAAA_AAAA; VOL / 64];
+    let bytes = named_chunk(&["test:dirt", "test:stone"], 1, &words);
+
+    let mut block_table = BlockTable::default();
+    let chunk = chunk_format::decode(&mut bytes.as_slice(), &block_library, &block_table).unwrap();
+
+    let mut encoded = Vec::new();
+    chunk_format::encode(&chunk, &block_library, &mut block_table, &mut encoded).unwrap();
+    assert_eq!(encoded[4..6], chunk_format::VERSION.to_le_bytes());
+
+    let decoded =
+        chunk_format::decode(&mut encoded.as_slice(), &block_library, &block_table).unwrap();
+    assert_same_voxels(&decoded, &chunk);
+}
+
+#[test]
+fn rejects_unknown_chunk_versions() {
+    let block_library = library();
+    let mut bytes = named_chunk(&["test:dirt"], 1, &vec![0; VOL / 64]);
+    bytes[4..6].copy_from_slice(&(chunk_format::VERSION + 1).to_le_bytes());
+
+    let result = chunk_format::decode(
+        &mut bytes.as_slice(),
+        &block_library,
+        &BlockTable::default(),
+    );
+    assert!(result.is_err());
+}
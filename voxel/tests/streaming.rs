@@ -0,0 +1,165 @@
+mod common;
+
+use bevy::{
+    math::{IVec3, UVec3},
+    tasks::{AsyncComputeTaskPool, TaskPool},
+};
+use voxel::{
+    block_lib::BlockLibrary,
+    chunk::{
+        ChunkMap, ChunkPos, DirtyChunks, SaveTasks,
+        generator::{FlatGenerator, StoredGenerator, TerrainGenerator},
+    },
+    edit::EditedChunks,
+    save::ChunkStorage,
+};
+
+use common::{TempDir, block, block_library};
+
+// Stone up to `y = 10`. The edited voxel is on the `pos_x` border of the
+// chunk at the origin, mirrored into the padding of the chunk past it.
+
+const EDITED: IVec3 = IVec3::new(62, 5, 5);
+const CHUNK: ChunkPos = IVec3::ZERO;
+const NEIGHBOUR: ChunkPos = IVec3::X;
+/// `EDITED` in the padding of `NEIGHBOUR`.
+const PADDING: UVec3 = UVec3::new(0, 5, 5);
+
+struct World {
+    block_library: BlockLibrary,
+    generator: TerrainGenerator,
+    chunk_map: ChunkMap,
+    edited_chunks: EditedChunks,
+    save_tasks: SaveTasks,
+}
+
+impl World {
+    fn new(storage: Option<&ChunkStorage>) -> Self {
+        AsyncComputeTaskPool::get_or_init(TaskPool::new);
+
+        let block_library = block_library([("test:stone", block())]);
+
+        let flat = FlatGenerator::new(10, "test:stone");
+        let generator = match storage {
+            Some(storage) => TerrainGenerator::new(0, StoredGenerator::new(storage.clone(), flat)),
+            None => TerrainGenerator::new(0, flat),
+        };
+
+        let world = Self {
+            block_library,
+            generator,
+            chunk_map: ChunkMap::default(),
+            edited_chunks: EditedChunks::default(),
+            save_tasks: SaveTasks::default(),
+        };
+
+        for chunk_pos in [CHUNK, NEIGHBOUR, IVec3::Z * 5] {
+            world.load(chunk_pos);
+        }
+
+        world
+    }
+
+    fn load(&self, chunk_pos: ChunkPos) {
+        let chunk = self.generator.generate(chunk_pos, &self.block_library);
+        self.chunk_map.insert(chunk_pos, chunk);
+        self.chunk_map.link_padding(chunk_pos, &self.block_library);
+    }
+
+    /// Carves `voxel_pos` like a `TerrainEditor`.
+    fn edit(&mut self, voxel_pos: IVec3) {
+        let mut changed = DirtyChunks::default();
+        let mut changes = Vec::new();
+        self.chunk_map.set_voxel(
+            voxel_pos,
+            None,
+            &self.block_library,
+            &mut changed,
+            Some(&mut changes),
+        );
+        self.edited_chunks.extend(changed.iter().copied());
+        self.edited_chunks.record(&changes);
+    }
+
+    /// Unloads `chunk_pos` and waits until it's saved.
+    fn unload(&mut self, chunk_pos: ChunkPos, storage: Option<&ChunkStorage>) -> Vec<ChunkPos> {
+        let changed = self.chunk_map.unload(
+            chunk_pos,
+            &mut self.edited_chunks,
+            storage,
+            &mut self.save_tasks,
+            &self.block_library,
+        );
+        assert!(!self.chunk_map.contains_key(&chunk_pos));
+
+        while !self.save_tasks.is_empty() {
+            self.save_tasks.poll(|_, _, saved| saved.unwrap());
+        }
+
+        changed
+    }
+
+    fn padding_is_stone(&self) -> bool {
+        self.chunk_map
+            .get(&NEIGHBOUR)
+            .unwrap()
+            .get(PADDING)
+            .is_some()
+    }
+}
+
+#[test]
+fn edits_survive_unloading() {
+    let dir = TempDir::new("unload");
+    let storage = ChunkStorage::new(&*dir).unwrap();
+    let mut world = World::new(Some(&storage));
+
+    world.edit(EDITED);
+    assert!(world.edited_chunks.contains(&CHUNK));
+
+    // the padding of the neighbour already matches what will load
+    assert!(world.unload(CHUNK, Some(&storage)).is_empty());
+    assert!(!world.edited_chunks.contains(&CHUNK));
+    assert!(!world.padding_is_stone());
+
+    world.load(CHUNK);
+    assert_eq!(world.chunk_map.get_voxel(EDITED), Some(None));
+    assert!(!world.padding_is_stone());
+}
+
+#[test]
+fn unedited_chunks_are_not_saved() {
+    let dir = TempDir::new("unedited");
+    let storage = ChunkStorage::new(&*dir).unwrap();
+    let mut world = World::new(Some(&storage));
+
+    world.edit(EDITED);
+    world.unload(IVec3::Z * 5, Some(&storage));
+
+    assert!(!storage.contains_chunk(IVec3::Z * 5).unwrap());
+}
+
+#[test]
+fn unsaved_edits_are_dropped_from_the_padding() {
+    let mut world = World::new(None);
+
+    world.edit(EDITED);
+    assert!(!world.padding_is_stone());
+
+    // the neighbour mirrors the chunk as it will be generated again
+    assert_eq!(world.unload(CHUNK, None), vec![NEIGHBOUR]);
+    assert!(world.padding_is_stone());
+
+    world.load(CHUNK);
+    let stone = world.block_library.lookup("test:stone");
+    assert_eq!(world.chunk_map.get_voxel(EDITED), Some(stone));
+}
+
+#[test]
+fn interior_edits_leave_the_padding_alone() {
+    let mut world = World::new(None);
+
+    world.edit(EDITED - IVec3::X * 10);
+    assert!(world.unload(CHUNK, None).is_empty());
+    assert!(world.padding_is_stone());
+}